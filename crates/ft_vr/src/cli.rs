@@ -0,0 +1,67 @@
+use std::path::PathBuf;
+use clap::{Parser, Subcommand, ValueEnum};
+use log::LevelFilter;
+
+/// ftVR core: renders the headset, drives the car and serves the wizard
+#[derive(Parser, Debug)]
+#[command(name = "ft_vr", version)]
+pub struct Cli {
+    /// Config file, created with defaults if it does not exist
+    #[arg(long, short, value_name = "FILE", default_value = "conf.ron", global = true)]
+    pub config: PathBuf,
+
+    /// Fullscreen window, the default if built with the `fullscreen` feature
+    #[arg(long, conflicts_with_all = ["windowed", "headless"])]
+    pub fullscreen: bool,
+
+    /// Normal window, even if built with the `fullscreen` feature
+    #[arg(long, conflicts_with = "headless")]
+    pub windowed: bool,
+
+    /// No window, only input devices, game logic and the websocket (see `headless` in the config)
+    #[arg(long)]
+    pub headless: bool,
+
+    /// Most verbose log level shown, `RUST_LOG` can still narrow it down per module
+    #[arg(long, value_name = "LEVEL", default_value_t = LevelFilter::Trace, value_parser = parse_level, global = true)]
+    pub log_level: LevelFilter,
+
+    /// Do not send tracing spans to Tracy
+    #[arg(long)]
+    pub no_tracing: bool,
+
+    /// Fake headset, wheel and pedal instead of looking for hardware
+    #[arg(long)]
+    pub simulate: bool,
+
+    /// Writes every bus message to FILE as JSON lines
+    #[arg(long, value_name = "FILE")]
+    pub record: Option<PathBuf>,
+
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+fn parse_level(level: &str) -> Result<LevelFilter, String> {
+    level.parse().map_err(|_| "expected off, error, warn, info, debug or trace".to_string())
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Lists the input devices that were detected and exits
+    Devices,
+    /// Writes the leaderboard, fastest first, and exits
+    ExportLeaderboard {
+        #[arg(long, value_enum, default_value_t = ExportFormat::Csv)]
+        format: ExportFormat,
+        /// Standard output if not given
+        #[arg(long, short, value_name = "FILE")]
+        output: Option<PathBuf>,
+    },
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+pub enum ExportFormat {
+    Csv,
+    Json,
+}
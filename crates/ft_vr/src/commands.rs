@@ -0,0 +1,53 @@
+use std::fs::File;
+use std::io::{stdout, Write};
+use std::path::PathBuf;
+use pub_sub::PubSub;
+use input_devices::InputDevices;
+use messages::file_config::read_config;
+use messages::{DriverState, VrMessage};
+use crate::cli::ExportFormat;
+
+pub fn list_devices() {
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .unwrap();
+    let bus = PubSub::<VrMessage>::new();
+    let devices = runtime.block_on(InputDevices::new(&bus));
+
+    for state in devices.driver_states() {
+        match state {
+            DriverState::Online { name } => println!("{:<20} online", name),
+            DriverState::Offline { name } => println!("{:<20} not found", name),
+        }
+    }
+}
+
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+pub fn export_leaderboard(format: ExportFormat, output: Option<PathBuf>) -> std::io::Result<()> {
+    let mut leaderboard = read_config().leaderboard;
+    leaderboard.sort_by(|a, b| a.time.total_cmp(&b.time));
+
+    let exported = match format {
+        ExportFormat::Csv => {
+            let mut csv = "rank,name,time,id\n".to_string();
+            for (rank, entry) in leaderboard.iter().enumerate() {
+                csv += &format!("{},{},{:.2},{}\n", rank + 1, csv_field(&entry.name), entry.time, entry.id);
+            }
+            csv
+        }
+        ExportFormat::Json => serde_json::to_string_pretty(&leaderboard)? + "\n",
+    };
+
+    match output {
+        Some(path) => File::create(path)?.write_all(exported.as_bytes()),
+        None => stdout().write_all(exported.as_bytes()),
+    }
+}
@@ -1,14 +1,22 @@
+use std::thread::JoinHandle;
 use std::time::{Duration, Instant};
 use log::LevelFilter;
-use pub_sub::PubSub;
+use pub_sub::{PubSub, Subscription};
 use tokio::time::sleep;
 use tracing_subscriber::layer::SubscriberExt;
 use tracing_tracy::client::ProfiledAllocator;
+use clap::Parser;
 use game_core::game_main;
 use input_devices::InputDevices;
+use messages::file_config::set_config_path;
 use messages::{LogMessageType, VrMessage};
-use vr_renderer::vr_render_main;
+use vr_renderer::{vr_headless_main, vr_render_main, FULLSCREEN_BY_DEFAULT};
 use websocket_server::websocket_server;
+use crate::cli::{Cli, Command};
+
+mod cli;
+mod commands;
+mod recorder;
 
 // #[global_allocator]
 // static GLOBAL: ProfiledAllocator<std::alloc::System> = ProfiledAllocator::new(std::alloc::System, 100);
@@ -16,10 +24,10 @@ use websocket_server::websocket_server;
 #[macro_use]
 extern crate log;
 
-fn init_logging() {
+fn init_logging(level: LevelFilter) {
     pretty_env_logger::formatted_timed_builder()
         .parse_default_env()
-        .filter_level(LevelFilter::Trace)
+        .filter_level(level)
         .filter_module("calloop", LevelFilter::Info)
         .filter_module("naga", LevelFilter::Info)
         .filter_module("wgpu", LevelFilter::Warn)
@@ -34,12 +42,28 @@ fn init_logging() {
         .init();
 }
 
-async fn input_device_loop(bus: PubSub<VrMessage>) {
-    let mut input_devices = InputDevices::new(&bus).await;
+/// How long the renderer waits for the other subsystems before the process exits anyway
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(5);
+
+fn shutdown_requested(subscription: &Subscription<VrMessage>) -> bool {
+    let mut requested = false;
+    while let Ok(msg) = subscription.try_recv() {
+        requested |= matches!(msg, VrMessage::Shutdown {});
+    }
+    requested
+}
+
+async fn input_device_loop(bus: PubSub<VrMessage>, simulate: bool) {
+    let shutdown = bus.subscribe();
+    let mut input_devices = if simulate {
+        InputDevices::simulated(&bus)
+    } else {
+        InputDevices::new(&bus).await
+    };
     let states = input_devices.driver_states();
     let mut last_update = Instant::now();
     let mut device_drivers = input_devices.build().await;
-    loop {
+    while !shutdown_requested(&shutdown) {
         let mut errors = vec![];
         for driver in &mut device_drivers {
             if let Err(e) = driver.process().await {
@@ -62,6 +86,36 @@ async fn input_device_loop(bus: PubSub<VrMessage>) {
 
         sleep(Duration::from_millis(40)).await;
     }
+
+    for driver in &mut device_drivers {
+        if let Err(e) = driver.shutdown().await {
+            error!("Error shutting down input device: {:?}", e);
+        }
+    }
+}
+
+/// Ctrl+C goes through the same shutdown as closing the window
+async fn ctrl_c(bus: PubSub<VrMessage>) {
+    if tokio::signal::ctrl_c().await.is_ok() {
+        info!("Ctrl+C received, shutting down");
+        let _ = bus.send(VrMessage::Shutdown {});
+    }
+}
+
+/// Waits for the subsystem threads to finish after `VrMessage::Shutdown`
+fn join_all(threads: Vec<(&str, JoinHandle<()>)>) {
+    let deadline = Instant::now() + SHUTDOWN_TIMEOUT;
+    for (name, thread) in threads {
+        while !thread.is_finished() && Instant::now() < deadline {
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        if thread.is_finished() {
+            let _ = thread.join();
+            info!("{} stopped", name);
+        } else {
+            warn!("{} did not stop in time", name);
+        }
+    }
 }
 
 fn init_tracing() {
@@ -80,24 +134,63 @@ macro_rules! spawn_future_in_thread {
                 .build()
                 .unwrap();
             rt.block_on($f);
-        });
+        })
     };
 }
 
 fn main() {
-    init_logging();
-    init_tracing();
+    let cli = Cli::parse();
+    init_logging(cli.log_level);
+    set_config_path(&cli.config);
+
+    match cli.command {
+        Some(Command::Devices) => {
+            commands::list_devices();
+            return;
+        }
+        Some(Command::ExportLeaderboard { format, output }) => {
+            if let Err(e) = commands::export_leaderboard(format, output) {
+                error!("Failed to export the leaderboard: {}", e);
+                std::process::exit(1);
+            }
+            return;
+        }
+        None => {}
+    }
+
+    if !cli.no_tracing {
+        init_tracing();
+    }
 
     let bus = PubSub::<VrMessage>::new();
+    let simulate = cli.simulate;
 
     let bus_game = bus.clone();
     let bus_input = bus.clone();
     let bus_ws = bus.clone();
+    let bus_signal = bus.clone();
 
-    spawn_future_in_thread!(input_device_loop(bus_input));
-    spawn_future_in_thread!(game_main(bus_game));
-    spawn_future_in_thread!(async {websocket_server(bus_ws)});
+    let mut threads = vec![
+        ("Input devices", spawn_future_in_thread!(input_device_loop(bus_input, simulate))),
+        ("Game core", spawn_future_in_thread!(game_main(bus_game))),
+        ("Websocket server", spawn_future_in_thread!(async {websocket_server(bus_ws)})),
+    ];
+    spawn_future_in_thread!(ctrl_c(bus_signal));
 
-    info!("Starting VR Renderer");
-    vr_render_main(bus.clone());
+    if let Some(path) = &cli.record {
+        match recorder::record(&bus, path) {
+            Ok(recorder) => threads.push(("Recorder", recorder)),
+            Err(e) => error!("Failed to start recording to {}: {}", path.display(), e),
+        }
+    }
+
+    // control plane only, for a server or CI without a display
+    if cli.headless {
+        info!("Starting headless");
+        vr_headless_main(bus.clone(), move || join_all(threads));
+    } else {
+        info!("Starting VR Renderer");
+        let fullscreen = cli.fullscreen || (FULLSCREEN_BY_DEFAULT && !cli.windowed);
+        vr_render_main(bus.clone(), fullscreen, move || join_all(threads));
+    }
 }
@@ -0,0 +1,46 @@
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::thread::JoinHandle;
+use std::time::Instant;
+use pub_sub::PubSub;
+use serde::Serialize;
+use messages::VrMessage;
+
+#[derive(Serialize)]
+struct Recorded<'a> {
+    /// Milliseconds since the recording started
+    at: u128,
+    message: &'a VrMessage,
+}
+
+/// Writes every bus message except those with a PIN to `path`, one JSON object per line, until `VrMessage::Shutdown`
+pub fn record(bus: &PubSub<VrMessage>, path: &Path) -> std::io::Result<JoinHandle<()>> {
+    let mut file = BufWriter::new(File::create(path)?);
+    let subscription = bus.subscribe();
+
+    std::thread::Builder::new()
+        .name("recorder".to_string())
+        .spawn(move || {
+            let start = Instant::now();
+            while let Ok(message) = subscription.recv() {
+                if message.contains_pin() {
+                    continue;
+                }
+                let recorded = Recorded { at: start.elapsed().as_millis(), message: &message };
+                let written = serde_json::to_writer(&mut file, &recorded)
+                    .map_err(std::io::Error::from)
+                    .and_then(|_| file.write_all(b"\n"));
+                if let Err(e) = written {
+                    error!("Failed to record bus message: {}", e);
+                    break;
+                }
+                if let VrMessage::Shutdown {} = message {
+                    break;
+                }
+            }
+            if let Err(e) = file.flush() {
+                error!("Failed to finish the recording: {}", e);
+            }
+        })
+}
@@ -1,46 +1,185 @@
+use std::fmt::{Display, Formatter};
+use std::thread;
+use std::time::Duration;
 use anyhow::bail;
-use pub_sub::{PubSub, Subscription};
-use messages::{Interface, VrMessage};
+use log::warn;
+use pub_sub::PubSub;
+use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver};
+use messages::{Interface, InterfaceResponse, VrMessage};
+
+/// Why waiting on the bus stopped without the expected message
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WaitError {
+    /// Someone sent `VrMessage::CancelInterface`
+    Cancelled,
+    TimedOut,
+    /// The bus is gone, nothing will ever arrive
+    Closed,
+}
+
+impl Display for WaitError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            WaitError::Cancelled => write!(f, "cancelled"),
+            WaitError::TimedOut => write!(f, "timed out"),
+            WaitError::Closed => write!(f, "bus closed"),
+        }
+    }
+}
+
+impl std::error::Error for WaitError {}
 
 pub struct AsyncBus {
     publication: PubSub<VrMessage>,
-    subscription: Subscription<VrMessage>,
+    inbox: UnboundedReceiver<VrMessage>,
 }
 
 impl AsyncBus {
+    /// The subscription blocks, so a thread hands its messages to a tokio channel that can be awaited
     pub fn new(pub_sub: &PubSub<VrMessage>) -> Self {
+        Self::filtered(pub_sub, |_| true)
+    }
+
+    /// Only forwards what `filter` accepts, a bus that is kept around for a single message does not pile up
+    /// everything else in the meantime
+    pub fn filtered(pub_sub: &PubSub<VrMessage>, filter: impl Fn(&VrMessage) -> bool + Send + 'static) -> Self {
+        let subscription = pub_sub.subscribe();
+        let (sender, inbox) = unbounded_channel();
+
+        let spawned = thread::Builder::new()
+            .name("async-bus".to_string())
+            .spawn(move || {
+                while let Ok(message) = subscription.recv() {
+                    if !filter(&message) {
+                        continue;
+                    }
+                    if sender.send(message).is_err() {
+                        // the AsyncBus was dropped
+                        break;
+                    }
+                }
+            });
+        if let Err(e) = spawned {
+            warn!("Failed to spawn bus forwarding thread: {}", e);
+        }
+
         Self {
             publication: pub_sub.clone(),
-            subscription: pub_sub.subscribe(),
+            inbox,
         }
     }
 
-    async fn wait_on_message(&mut self, message_filter: fn(&VrMessage) -> bool) -> VrMessage {
-        loop {
-            if let Ok(message) = self.subscription.try_recv() {
-                if message_filter(&message) {
-                    return message;
+    /// Drops everything that arrived so far, so answers to earlier interactions are not mistaken for new ones
+    pub fn discard_pending(&mut self) {
+        while self.inbox.try_recv().is_ok() {}
+    }
+
+    /// Waits for the first message `filter` accepts, without a timeout this is like a blocking receive
+    pub async fn wait_for(
+        &mut self,
+        mut filter: impl FnMut(&VrMessage) -> bool,
+        timeout: Option<Duration>,
+    ) -> Result<VrMessage, WaitError> {
+        let wait = async {
+            loop {
+                match self.inbox.recv().await {
+                    Some(message) if filter(&message) => return Ok(message),
+                    Some(_) => {}
+                    None => return Err(WaitError::Closed),
                 }
             }
+        };
 
-            tokio::task::yield_now().await;
+        match timeout {
+            Some(timeout) => tokio::time::timeout(timeout, wait).await.unwrap_or(Err(WaitError::TimedOut)),
+            None => wait.await,
         }
     }
 
-    pub async fn open_interface(&mut self, interface: Interface) -> anyhow::Result<()> {
+    fn show(&self, interface: Interface) -> anyhow::Result<()> {
         self.publication.send(VrMessage::ShowRenderedInterface { interface })?;
         Ok(())
     }
 
-    pub async fn confirm_interface(&mut self) -> anyhow::Result<i32> {
-        match self.wait_on_message(|m| matches!(m, VrMessage::InterfaceConfirm {..})).await {
-            VrMessage::InterfaceConfirm { data } => Ok(data),
-            _ => bail!("Unexpected message"),
-        }
+    pub async fn open_interface(&mut self, interface: Interface) -> anyhow::Result<()> {
+        self.discard_pending();
+        self.show(interface)
+    }
+
+    pub async fn hide_interface(&mut self) -> anyhow::Result<()> {
+        self.publication.send(VrMessage::HideRenderedInterface {})?;
+        Ok(())
+    }
+
+    /// Waits for the player's answer. `VrMessage::CancelInterface` or the timeout end the wait early,
+    /// the interface is closed again in that case.
+    pub async fn confirm_interface(&mut self, timeout: Option<Duration>) -> anyhow::Result<InterfaceResponse> {
+        let answer = self.wait_for(
+            |m| matches!(m, VrMessage::InterfaceConfirm {..} | VrMessage::CancelInterface {}),
+            timeout,
+        ).await;
+
+        let error = match answer {
+            Ok(VrMessage::InterfaceConfirm { data }) => return Ok(data),
+            Ok(_) => WaitError::Cancelled,
+            Err(e) => e,
+        };
+        self.hide_interface().await?;
+        Err(error.into())
     }
 
-    pub async fn open_interface_and_confirm(&mut self, interface: Interface) -> anyhow::Result<i32> {
+    pub async fn open_interface_and_confirm(&mut self, interface: Interface) -> anyhow::Result<InterfaceResponse> {
         self.open_interface(interface).await?;
-        self.confirm_interface().await
+        self.confirm_interface(None).await
     }
-}
\ No newline at end of file
+
+    pub async fn open_interface_and_confirm_within(&mut self, interface: Interface, timeout: Duration) -> anyhow::Result<InterfaceResponse> {
+        self.open_interface(interface).await?;
+        self.confirm_interface(Some(timeout)).await
+    }
+
+    pub async fn ask_number(&mut self, text: String, timeout: Option<Duration>) -> anyhow::Result<i32> {
+        self.open_interface(Interface::InputNumberAndConfirm { text }).await?;
+        match self.confirm_interface(timeout).await? {
+            InterfaceResponse::Number(number) => Ok(number),
+            other => bail!("Expected a number, got {:?}", other),
+        }
+    }
+
+    pub async fn choose(&mut self, title: String, options: Vec<String>) -> anyhow::Result<usize> {
+        match self.open_interface_and_confirm(Interface::Menu { title, options }).await? {
+            InterfaceResponse::Option(index) => Ok(index),
+            other => bail!("Expected an option, got {:?}", other),
+        }
+    }
+
+    pub async fn confirm(&mut self, question: String) -> anyhow::Result<bool> {
+        match self.open_interface_and_confirm(Interface::Confirm { question }).await? {
+            InterfaceResponse::Confirmed(confirmed) => Ok(confirmed),
+            other => bail!("Expected a confirmation, got {:?}", other),
+        }
+    }
+
+    pub async fn enter_text(&mut self, prompt: String, max_length: u8) -> anyhow::Result<String> {
+        match self.open_interface_and_confirm(Interface::TextEntry { prompt, max_length }).await? {
+            InterfaceResponse::Text(text) => Ok(text),
+            other => bail!("Expected text, got {:?}", other),
+        }
+    }
+
+    /// Returns once the countdown ran out in the headset
+    pub async fn countdown(&mut self, text: String, seconds: u32) -> anyhow::Result<()> {
+        match self.open_interface_and_confirm(Interface::Countdown { text, seconds }).await? {
+            InterfaceResponse::Done => Ok(()),
+            other => bail!("Expected the countdown to finish, got {:?}", other),
+        }
+    }
+
+    pub async fn show_progress(&mut self, text: String, progress: f32) -> anyhow::Result<()> {
+        self.show(Interface::Progress { text, progress })
+    }
+
+    pub async fn toast(&mut self, text: String, duration_ms: u32) -> anyhow::Result<()> {
+        self.show(Interface::Toast { text, duration_ms })
+    }
+}
@@ -1,50 +1,24 @@
+use std::sync::{Arc, Mutex};
 use pub_sub::PubSub;
-use tokio::task::JoinSet;
-use messages::LogMessageType::Error;
 use messages::VrMessage;
-use unit::GameCoreUnit;
+use registry::UnitRegistry;
 
 pub mod unit;
-mod api;
-
-struct Units {
-    units: Vec<Box<dyn GameCoreUnit + Send>>,
-}
-
-impl Units {
-    fn from_bus(bus: &PubSub<VrMessage>) -> Self {
-        Self {
-            units: vec![
-                Box::new(unit::pinentry::PinEntry::new(&bus)),
-                Box::new(unit::leaderboard::Leaderboard::new(&bus)),
-            ],
-        }
-    }
-
-    fn finish(self) -> Vec<Box<dyn GameCoreUnit + Send>> {
-        self.units
-    }
-}
-
-async fn unit_loop(mut unit: Box<dyn GameCoreUnit + Send>, bus: PubSub<VrMessage>) {
-    loop {
-        let result = unit.process().await;
-        if let Err(e) = result {
-            let _ = bus.send(VrMessage::Log {
-                message: format!("Error: {}", e),
-                message_type: Error,
-            });
-        }
-    }
+pub mod api;
+pub mod registry;
+
+/// Every unit `game_core` ships with, `units.enabled` in the config decides which ones run
+pub fn default_units() -> UnitRegistry {
+    let lockout = Arc::new(Mutex::new(unit::access::Lockout::default()));
+
+    UnitRegistry::default()
+        .register("pinentry", |bus| Box::new(unit::pinentry::PinEntry::new(bus)))
+        .register("leaderboard", |bus| Box::new(unit::leaderboard::Leaderboard::new(bus)))
+        .register("nameentry", |bus| Box::new(unit::nameentry::NameEntry::new(bus)))
+        .register("session", |bus| Box::new(unit::session::Session::new(bus)))
+        .register("access", move |bus| Box::new(unit::access::Access::new(bus, lockout.clone())))
 }
 
 pub async fn game_main(bus: PubSub<VrMessage>) {
-    let mut units = Units::from_bus(&bus).finish();
-    let mut join_set = JoinSet::new();
-
-    while let Some(unit) = units.pop() {
-        join_set.spawn(unit_loop(unit, bus.clone()));
-    }
-
-    join_set.join_all().await;
-}
\ No newline at end of file
+    default_units().run(bus).await;
+}
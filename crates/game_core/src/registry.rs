@@ -0,0 +1,208 @@
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use log::warn;
+use pub_sub::PubSub;
+use tokio::sync::watch;
+use tokio::task::JoinSet;
+use messages::file_config::{read_config, update_config};
+use messages::LogMessageType::Error;
+use messages::{UnitSettings, UnitState, UnitStatus, VrMessage};
+use crate::api::{AsyncBus, WaitError};
+use crate::unit::GameCoreUnit;
+
+const STATE_INTERVAL: Duration = Duration::from_secs(1);
+
+type UnitFactory = Box<dyn Fn(&PubSub<VrMessage>) -> Box<dyn GameCoreUnit + Send> + Send + Sync>;
+type States = Arc<Mutex<BTreeMap<&'static str, UnitState>>>;
+
+/// The units `game_main` runs, by name. Whether a unit runs is looked up in `units.enabled` in the config
+/// and can be changed at runtime with `VrMessage::SetUnitEnabled`.
+#[derive(Default)]
+pub struct UnitRegistry {
+    units: Vec<(&'static str, UnitFactory)>,
+}
+
+/// Why a unit stopped running
+enum Stop {
+    Disabled,
+    Failing,
+    Shutdown,
+}
+
+impl UnitRegistry {
+    pub fn register(
+        mut self,
+        name: &'static str,
+        factory: impl Fn(&PubSub<VrMessage>) -> Box<dyn GameCoreUnit + Send> + Send + Sync + 'static,
+    ) -> Self {
+        self.units.push((name, Box::new(factory)));
+        self
+    }
+
+    pub async fn run(self, bus: PubSub<VrMessage>) {
+        let settings = read_config().units;
+        let states: States = Arc::new(Mutex::new(BTreeMap::new()));
+        let mut switches = HashMap::new();
+        let mut join_set = JoinSet::new();
+
+        for (name, factory) in self.units {
+            let (switch, enabled) = watch::channel(settings.is_enabled(name));
+            switches.insert(name, switch);
+            states.lock().unwrap().insert(name, UnitState {
+                name: name.to_string(),
+                status: UnitStatus::Starting,
+                errors: 0,
+                restarts: 0,
+            });
+            join_set.spawn(supervise(name, factory, bus.clone(), enabled, states.clone()));
+        }
+        join_set.spawn(control(bus, switches, states));
+
+        join_set.join_all().await;
+    }
+}
+
+fn update(states: &States, name: &str, change: impl FnOnce(&mut UnitState)) {
+    if let Some(state) = states.lock().unwrap().get_mut(name) {
+        change(state);
+    }
+}
+
+fn backoff(settings: &UnitSettings, failures: u32) -> Duration {
+    let factor = 2u64.saturating_pow(failures.saturating_sub(1));
+    Duration::from_millis(settings.backoff_ms.saturating_mul(factor).min(settings.max_backoff_ms))
+}
+
+fn report(bus: &PubSub<VrMessage>, states: &States, name: &str, error: anyhow::Error) {
+    update(states, name, |state| state.errors += 1);
+    let _ = bus.send(VrMessage::Log {
+        message: format!("Error in {}: {}", name, error),
+        message_type: Error,
+    });
+}
+
+/// Calls `process` until the unit failed `restart_after` times in a row, backing off after every failure
+async fn run_unit(name: &'static str, unit: &mut (dyn GameCoreUnit + Send), bus: &PubSub<VrMessage>, states: &States) {
+    let settings = read_config().units;
+
+    if let Err(e) = unit.init().await {
+        report(bus, states, name, e);
+        tokio::time::sleep(backoff(&settings, 1)).await;
+        return;
+    }
+    update(states, name, |state| state.status = UnitStatus::Running);
+
+    let mut failures = 0;
+    loop {
+        match unit.process().await {
+            Ok(()) => failures = 0,
+            Err(e) => {
+                report(bus, states, name, e);
+                failures += 1;
+                if failures >= settings.restart_after.max(1) {
+                    return;
+                }
+                tokio::time::sleep(backoff(&settings, failures)).await;
+            }
+        }
+    }
+}
+
+/// Resolves once `control` dropped the unit's switch, which is how the registry shuts down
+async fn closed(enabled: &mut watch::Receiver<bool>) {
+    let _ = enabled.wait_for(|_| false).await;
+}
+
+async fn supervise(
+    name: &'static str,
+    factory: UnitFactory,
+    bus: PubSub<VrMessage>,
+    mut enabled: watch::Receiver<bool>,
+    states: States,
+) {
+    loop {
+        if !*enabled.borrow_and_update() {
+            update(&states, name, |state| state.status = UnitStatus::Disabled);
+            if enabled.wait_for(|enabled| *enabled).await.is_err() {
+                return;
+            }
+        }
+
+        update(&states, name, |state| state.status = UnitStatus::Starting);
+        let mut unit = factory(&bus);
+        let stop = tokio::select! {
+            _ = run_unit(name, unit.as_mut(), &bus, &states) => Stop::Failing,
+            disabled = enabled.wait_for(|enabled| !*enabled) => match disabled {
+                Ok(_) => Stop::Disabled,
+                Err(_) => Stop::Shutdown,
+            },
+        };
+
+        if let Err(e) = unit.shutdown().await {
+            report(&bus, &states, name, e);
+        }
+        drop(unit);
+
+        match stop {
+            Stop::Shutdown => return,
+            Stop::Disabled => {}
+            Stop::Failing => {
+                warn!("Restarting unit {}", name);
+                update(&states, name, |state| {
+                    state.status = UnitStatus::Restarting;
+                    state.restarts += 1;
+                });
+                let settings = read_config().units;
+                tokio::select! {
+                    _ = tokio::time::sleep(backoff(&settings, settings.restart_after)) => {}
+                    _ = closed(&mut enabled) => return,
+                }
+            }
+        }
+    }
+}
+
+/// Switches units on and off and reports their states, like the input devices do with `DriverStateUpdate`.
+/// Returns on `VrMessage::Shutdown`, dropping the switches lets every unit shut down.
+async fn control(bus: PubSub<VrMessage>, switches: HashMap<&'static str, watch::Sender<bool>>, states: States) {
+    let mut async_bus = AsyncBus::new(&bus);
+    let mut last_update = Instant::now();
+
+    loop {
+        let until_update = STATE_INTERVAL.saturating_sub(last_update.elapsed());
+        let message = async_bus.wait_for(
+            |m| matches!(m, VrMessage::SetUnitEnabled {..} | VrMessage::Shutdown {}),
+            Some(until_update),
+        ).await;
+
+        if let Ok(VrMessage::Shutdown {}) | Err(WaitError::Closed) = message {
+            return;
+        }
+        if let Ok(VrMessage::SetUnitEnabled { name, enabled }) = message {
+            match switches.get(name.as_str()) {
+                Some(switch) => {
+                    switch.send_replace(enabled);
+                    if let Err(e) = update_config(|config| { config.units.enabled.insert(name, enabled); }) {
+                        let _ = bus.send(VrMessage::Log {
+                            message: format!("Failed to save the config: {}", e),
+                            message_type: Error,
+                        });
+                    }
+                }
+                None => {
+                    let _ = bus.send(VrMessage::Log {
+                        message: format!("There is no unit called {}", name),
+                        message_type: Error,
+                    });
+                }
+            }
+        }
+
+        if last_update.elapsed() >= STATE_INTERVAL {
+            last_update = Instant::now();
+            let states = states.lock().unwrap().values().cloned().collect();
+            let _ = bus.send(VrMessage::UnitStateUpdate { states });
+        }
+    }
+}
@@ -1,10 +1,25 @@
 pub mod pinentry;
 pub mod leaderboard;
+pub mod nameentry;
+pub mod session;
+pub mod access;
 
 use anyhow::Result;
 use async_trait::async_trait;
 
+/// A piece of game logic, run by the `UnitRegistry`. `process` is called again and again until
+/// the unit is disabled, failing too often gets it shut down and created again.
 #[async_trait]
 pub trait GameCoreUnit {
+    /// Runs once before the first `process`
+    async fn init(&mut self) -> Result<()> {
+        Ok(())
+    }
+
     async fn process(&mut self) -> Result<()>;
+
+    /// Runs before the unit is dropped, `process` may have been interrupted at any await
+    async fn shutdown(&mut self) -> Result<()> {
+        Ok(())
+    }
 }
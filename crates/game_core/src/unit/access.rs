@@ -0,0 +1,195 @@
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use anyhow::Result;
+use async_trait::async_trait;
+use log::warn;
+use pub_sub::PubSub;
+use sha2::{Digest, Sha256};
+use messages::file_config::{read_config, update_config};
+use messages::{AccessControl, LogMessageType, VrMessage};
+use crate::api::{AsyncBus, WaitError};
+use crate::unit::GameCoreUnit;
+
+fn hash_pin(salt: &str, pin: &str) -> String {
+    format!("{:x}", Sha256::digest(format!("{}{}", salt, pin).as_bytes()))
+}
+
+fn new_pin_hash(pin: &str) -> String {
+    let nanos = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_nanos();
+    let salt = format!("{:x}", nanos);
+    format!("{}${}", salt, hash_pin(&salt, pin))
+}
+
+/// The PIN must not end up in the audit log
+fn describe(command: &VrMessage) -> String {
+    match command {
+        VrMessage::SetPin { .. } => "SetPin".to_string(),
+        command => format!("{:?}", command),
+    }
+}
+
+fn verify_pin(pin_hash: &str, pin: &str) -> bool {
+    match pin_hash.split_once('$') {
+        Some((salt, hash)) => hash_pin(salt, pin) == hash,
+        None => false,
+    }
+}
+
+/// Wrong PINs in a row and the lockout they caused. Shared by every `Access` the registry builds, so
+/// disabling and enabling the unit or letting it fail until it restarts does not reset them.
+#[derive(Default)]
+pub struct Lockout {
+    failed_attempts: u32,
+    locked_out_until: Option<Instant>,
+}
+
+/// Lets privileged commands (`VrMessage::is_privileged`) through to the bus only after the operator PIN
+/// was verified. The websocket server refuses them unwrapped, so the wizard sends them as `Privileged`.
+pub struct Access {
+    bus: PubSub<VrMessage>,
+    async_bus: AsyncBus,
+    unlocked_until: Option<Instant>,
+    lockout: Arc<Mutex<Lockout>>,
+}
+
+impl Access {
+    pub fn new(bus: &PubSub<VrMessage>, lockout: Arc<Mutex<Lockout>>) -> Self {
+        Self {
+            bus: bus.clone(),
+            async_bus: AsyncBus::new(bus),
+            unlocked_until: None,
+            lockout,
+        }
+    }
+
+    fn audit(&self, config: &AccessControl, event: &str) {
+        let line = format!("{} {}\n", chrono::Local::now().format("%Y-%m-%d %H:%M:%S"), event);
+        let written = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&config.audit_log)
+            .and_then(|mut file| file.write_all(line.as_bytes()));
+        if let Err(e) = written {
+            warn!("Failed to write audit log {}: {}", config.audit_log, e);
+        }
+    }
+
+    fn log(&self, message: String, message_type: LogMessageType) -> Result<()> {
+        self.bus.send(VrMessage::Log { message, message_type })?;
+        Ok(())
+    }
+
+    fn remaining(until: Option<Instant>) -> Option<Duration> {
+        until
+            .map(|until| until.saturating_duration_since(Instant::now()))
+            .filter(|remaining| !remaining.is_zero())
+    }
+
+    fn is_unlocked(&self, config: &AccessControl) -> bool {
+        config.pin_hash.is_none() || Self::remaining(self.unlocked_until).is_some()
+    }
+
+    fn publish_state(&self, config: &AccessControl) -> Result<()> {
+        let locked_out_until = self.lockout.lock().unwrap().locked_out_until;
+        self.bus.send(VrMessage::AccessState {
+            unlocked: self.is_unlocked(config),
+            locked_out_for: Self::remaining(locked_out_until).map_or(0, |remaining| remaining.as_secs()),
+            pin_configured: config.pin_hash.is_some(),
+        })?;
+        Ok(())
+    }
+
+    fn check_pin(&mut self, config: &AccessControl, pin: &str) -> Result<()> {
+        let Some(pin_hash) = &config.pin_hash else {
+            return Ok(());
+        };
+
+        let mut lockout = self.lockout.lock().unwrap();
+        if let Some(remaining) = Self::remaining(lockout.locked_out_until) {
+            self.audit(config, "PIN rejected, locked out");
+            return self.log(format!("PIN entry is locked for {} more seconds", remaining.as_secs()), LogMessageType::Error);
+        }
+
+        if verify_pin(pin_hash, pin) {
+            lockout.failed_attempts = 0;
+            self.unlocked_until = Some(Instant::now() + Duration::from_secs(config.unlock_seconds));
+            self.audit(config, "PIN verified, unlocked");
+            return self.log("Operator PIN verified".to_string(), LogMessageType::Info);
+        }
+
+        lockout.failed_attempts += 1;
+        self.audit(config, &format!("wrong PIN, attempt {}", lockout.failed_attempts));
+        if lockout.failed_attempts >= config.max_attempts.max(1) {
+            lockout.failed_attempts = 0;
+            lockout.locked_out_until = Some(Instant::now() + Duration::from_secs(config.lockout_seconds));
+            self.audit(config, &format!("locked out for {} seconds", config.lockout_seconds));
+            return self.log(format!("Too many wrong PINs, locked for {} seconds", config.lockout_seconds), LogMessageType::Error);
+        }
+        self.log("Wrong PIN".to_string(), LogMessageType::Warning)
+    }
+
+    fn run_privileged(&mut self, config: &AccessControl, command: VrMessage) -> Result<()> {
+        if !self.is_unlocked(config) {
+            self.audit(config, &format!("denied {}", describe(&command)));
+            return self.log("This needs the operator PIN, unlock first".to_string(), LogMessageType::Error);
+        }
+
+        self.audit(config, &format!("allowed {}", describe(&command)));
+        match command {
+            VrMessage::SetPin { pin } => {
+                let pin_hash = if pin.is_empty() { None } else { Some(new_pin_hash(&pin)) };
+                update_config(|config| config.access.pin_hash = pin_hash)?;
+                self.log("Operator PIN changed".to_string(), LogMessageType::Info)
+            }
+            command => {
+                self.bus.send(command)?;
+                Ok(())
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl GameCoreUnit for Access {
+    async fn init(&mut self) -> Result<()> {
+        self.publish_state(&read_config().access)
+    }
+
+    async fn process(&mut self) -> Result<()> {
+        // wake up when the unlock runs out, so the wizard sees it
+        let timeout = Self::remaining(self.unlocked_until);
+        let message = self.async_bus.wait_for(
+            |m| matches!(
+                m,
+                VrMessage::Privileged {..} | VrMessage::Unlock {} | VrMessage::Lock {} | VrMessage::VerifyOperatorPin {..}
+            ),
+            timeout,
+        ).await;
+
+        let config = read_config().access;
+        match message {
+            Ok(VrMessage::Unlock {}) => {
+                if config.pin_hash.is_some() {
+                    self.bus.send(VrMessage::AskOperatorPin { length: config.pin_length })?;
+                }
+            }
+            Ok(VrMessage::VerifyOperatorPin { pin }) => self.check_pin(&config, &pin)?,
+            Ok(VrMessage::Lock {}) => {
+                self.unlocked_until = None;
+                self.audit(&config, "locked");
+            }
+            Ok(VrMessage::Privileged { command }) => self.run_privileged(&config, *command)?,
+            Ok(_) => {}
+            Err(WaitError::TimedOut) => {
+                self.unlocked_until = None;
+                self.audit(&config, "unlock expired");
+            }
+            Err(e) => return Err(e.into()),
+        }
+
+        // the PIN may have just changed
+        self.publish_state(&read_config().access)
+    }
+}
@@ -1,8 +1,9 @@
 use std::time::Instant;
 use async_trait::async_trait;
-use pub_sub::{PubSub, Subscription};
-use messages::{LeaderboardEntry, VrMessage};
-use messages::file_config::{read_config, save_config};
+use pub_sub::PubSub;
+use messages::{LeaderboardEntry, SessionPhase, VrMessage};
+use messages::file_config::{read_config, update_config};
+use crate::api::AsyncBus;
 use crate::unit::GameCoreUnit;
 
 struct LeaderboardUser {
@@ -24,16 +25,15 @@ impl Into<LeaderboardEntry> for LeaderboardUser {
 
 pub struct Leaderboard {
     bus: PubSub<VrMessage>,
-    subscription: Subscription<VrMessage>,
+    async_bus: AsyncBus,
     start: Option<LeaderboardUser>,
 }
 
 impl Leaderboard {
     pub fn new(bus: &PubSub<VrMessage>) -> Self {
-        let subscription = bus.subscribe();
         Self {
             bus: bus.clone(),
-            subscription,
+            async_bus: AsyncBus::new(bus),
             start: None,
         }
     }
@@ -43,32 +43,41 @@ impl Leaderboard {
 #[async_trait]
 impl GameCoreUnit for Leaderboard {
     async fn process(&mut self) -> anyhow::Result<()> {
-        while let Ok(message) = self.subscription.try_recv() {
-            match message {
-                VrMessage::TimerStart { name } => {
-                    self.start = Some(LeaderboardUser {
-                        name,
-                        start: Instant::now(),
-                    });
-                }
-                VrMessage::TimerEnd {} => {
-                    if let Some(user) = self.start.take() {
-                        let entry: LeaderboardEntry = user.into();
+        let message = self.async_bus.wait_for(
+            |m| matches!(
+                m,
+                VrMessage::TimerStart {..}
+                    | VrMessage::TimerEnd {}
+                    | VrMessage::DeleteTimerEntry {..}
+                    | VrMessage::SessionState { phase: SessionPhase::Aborted }
+            ),
+            None,
+        ).await?;
 
-                        let mut config = read_config();
-                        config.leaderboard.push(entry.clone());
-                        save_config(&config);
+        match message {
+            VrMessage::TimerStart { name } => {
+                self.start = Some(LeaderboardUser {
+                    name,
+                    start: Instant::now(),
+                });
+            }
+            VrMessage::TimerEnd {} => {
+                if let Some(user) = self.start.take() {
+                    let entry: LeaderboardEntry = user.into();
 
-                        let _ = self.bus.send(VrMessage::PushTimerEntry { entry });
-                    }
-                }
-                VrMessage::DeleteTimerEntry { id } => {
-                    let mut config = read_config();
-                    config.leaderboard.retain(|entry| entry.id != id);
-                    save_config(&config);
+                    update_config(|config| config.leaderboard.push(entry.clone()))?;
+
+                    let _ = self.bus.send(VrMessage::PushTimerEntry { entry });
                 }
-                _ => {}
             }
+            VrMessage::SessionState { phase: SessionPhase::Aborted } => {
+                // an aborted run does not make it onto the leaderboard
+                self.start = None;
+            }
+            VrMessage::DeleteTimerEntry { id } => {
+                update_config(|config| config.leaderboard.retain(|entry| entry.id != id))?;
+            }
+            _ => {}
         }
         Ok(())
     }
@@ -0,0 +1,50 @@
+use async_trait::async_trait;
+use pub_sub::PubSub;
+use messages::{Interface, InterfaceResponse, VrMessage};
+use anyhow::{bail, Result};
+use crate::api::AsyncBus;
+use crate::unit::GameCoreUnit;
+
+const MAX_NAME_LENGTH: u8 = 12;
+
+/// Asks for the player's name with the headset keyboard until one is confirmed
+pub async fn ask_player_name(async_bus: &mut AsyncBus) -> Result<String> {
+    loop {
+        let data = async_bus.open_interface_and_confirm(Interface::TextEntry {
+            prompt: "Name:".to_string(),
+            max_length: MAX_NAME_LENGTH,
+        }).await?;
+
+        match data {
+            InterfaceResponse::Text(name) if !name.is_empty() => return Ok(name),
+            // confirmed without typing anything, ask again
+            InterfaceResponse::Text(_) => continue,
+            other => bail!("Expected a name, got {:?}", other),
+        }
+    }
+}
+
+/// Lets the player type their own name with the wheel before the timer starts
+pub struct NameEntry {
+    bus: PubSub<VrMessage>,
+    async_bus: AsyncBus,
+}
+
+impl NameEntry {
+    pub fn new(bus: &PubSub<VrMessage>) -> Self {
+        Self {
+            bus: bus.clone(),
+            async_bus: AsyncBus::new(&bus),
+        }
+    }
+}
+
+#[async_trait]
+impl GameCoreUnit for NameEntry {
+    async fn process(&mut self) -> Result<()> {
+        self.async_bus.wait_for(|m| matches!(m, VrMessage::AskPlayerName {}), None).await?;
+        let name = ask_player_name(&mut self.async_bus).await?;
+        self.bus.send(VrMessage::TimerStart { name })?;
+        Ok(())
+    }
+}
@@ -1,22 +1,23 @@
+use std::time::Duration;
 use async_trait::async_trait;
-use pub_sub::{PubSub, Subscription};
-use messages::{Interface, VrMessage};
+use pub_sub::PubSub;
+use messages::VrMessage;
 use anyhow::{bail, Result};
 use crate::api::AsyncBus;
 use crate::unit::GameCoreUnit;
 
+/// Per digit, an abandoned PIN prompt should not stay in the headset forever
+const PIN_TIMEOUT: Duration = Duration::from_secs(60);
+
 pub struct PinEntry {
     bus: PubSub<VrMessage>,
-    subscription: Subscription<VrMessage>,
     async_bus: AsyncBus,
 }
 
 impl PinEntry {
     pub fn new(bus: &PubSub<VrMessage>) -> Self {
-        let subscription = bus.subscribe();
         Self {
             bus: bus.clone(),
-            subscription,
             async_bus: AsyncBus::new(&bus),
         }
     }
@@ -24,9 +25,7 @@ impl PinEntry {
     async fn pin_entry(&mut self, pin_len: u8) -> Result<String> {
         let mut pin = String::new();
         for _ in 0..pin_len {
-            let data = self.async_bus.open_interface_and_confirm(Interface::InputNumberAndConfirm {
-                text: format!("PIN: {}", pin),
-            }).await?;
+            let data = self.async_bus.ask_number(format!("PIN: {}", pin), Some(PIN_TIMEOUT)).await?;
 
             pin.push_str(&data.to_string());
         }
@@ -38,14 +37,20 @@ impl PinEntry {
 #[async_trait]
 impl GameCoreUnit for PinEntry {
     async fn process(&mut self) -> Result<()> {
-        if let Ok(message) = self.subscription.try_recv() {
-            match message {
-                VrMessage::AskPin { length } => {
-                    let pin = self.pin_entry(length).await?;
-                    self.bus.send(VrMessage::ConfirmPin { pin })?;
-                }
-                _ => {}
+        let message = self.async_bus.wait_for(
+            |m| matches!(m, VrMessage::AskPin {..} | VrMessage::AskOperatorPin {..}),
+            None,
+        ).await?;
+        match message {
+            VrMessage::AskPin { length } => {
+                let pin = self.pin_entry(length).await?;
+                self.bus.send(VrMessage::ConfirmPin { pin })?;
+            }
+            VrMessage::AskOperatorPin { length } => {
+                let pin = self.pin_entry(length).await?;
+                self.bus.send(VrMessage::VerifyOperatorPin { pin })?;
             }
+            _ => {}
         }
         Ok(())
     }
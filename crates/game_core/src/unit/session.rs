@@ -0,0 +1,183 @@
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use pub_sub::PubSub;
+use tokio::task::JoinHandle;
+use messages::file_config::read_config;
+use messages::{LeaderboardEntry, SessionPhase, VrMessage};
+use crate::api::{AsyncBus, WaitError};
+use crate::unit::nameentry::ask_player_name;
+use crate::unit::GameCoreUnit;
+
+const COUNTDOWN_SECONDS: u32 = 3;
+/// How long the leaderboard gets to store the run after the timer stopped
+const ENTRY_TIMEOUT: Duration = Duration::from_secs(2);
+const RESULTS_DURATION: Duration = Duration::from_secs(8);
+/// How often the phase is sent again, drivers that connect later still learn it
+const PHASE_INTERVAL: Duration = Duration::from_secs(1);
+
+/// The phase the session is in, shared with the task that repeats it
+#[derive(Clone)]
+struct Phase {
+    bus: PubSub<VrMessage>,
+    current: Arc<Mutex<SessionPhase>>,
+}
+
+impl Phase {
+    fn enter(&self, phase: SessionPhase) -> Result<()> {
+        *self.current.lock().unwrap() = phase;
+        self.bus.send(VrMessage::SessionState { phase })?;
+        Ok(())
+    }
+
+    async fn repeat(self) {
+        loop {
+            tokio::time::sleep(PHASE_INTERVAL).await;
+            let phase = *self.current.lock().unwrap();
+            if self.bus.send(VrMessage::SessionState { phase }).is_err() {
+                return;
+            }
+        }
+    }
+}
+
+/// Takes a player through a whole parkour run:
+/// idle → player setup → calibration check → countdown → driving → finished/aborted → results → idle.
+/// `AbortSession` or cancelling one of the headset prompts ends the session early.
+/// Runs timed without a session (from the wizard or `AskPlayerName`) get a driving phase too, the car
+/// only takes throttle while driving.
+pub struct Session {
+    bus: PubSub<VrMessage>,
+    async_bus: AsyncBus,
+    /// Only gets `AbortSession`, kept for the unit's lifetime since every bus has a thread of its own
+    abort_bus: AsyncBus,
+    phase: Phase,
+    repeater: Option<JoinHandle<()>>,
+}
+
+impl Session {
+    pub fn new(bus: &PubSub<VrMessage>) -> Self {
+        Self {
+            bus: bus.clone(),
+            async_bus: AsyncBus::new(bus),
+            abort_bus: AsyncBus::filtered(bus, |m| matches!(m, VrMessage::AbortSession {})),
+            phase: Phase {
+                bus: bus.clone(),
+                current: Arc::new(Mutex::new(SessionPhase::Idle)),
+            },
+            repeater: None,
+        }
+    }
+
+    /// Everything up to the end of the timed run, returns the stored leaderboard entry if it arrived
+    async fn run(phase: &Phase, bus: &PubSub<VrMessage>, async_bus: &mut AsyncBus) -> Result<Option<LeaderboardEntry>> {
+        phase.enter(SessionPhase::PlayerSetup)?;
+        let name = ask_player_name(async_bus).await?;
+
+        phase.enter(SessionPhase::CalibrationCheck)?;
+        while !async_bus.confirm("Geradeaus schauen, Lenkrad in die Mitte".to_string()).await? {}
+        bus.send(VrMessage::SetGyroscopeZero {})?;
+        bus.send(VrMessage::ResetWheel {})?;
+
+        phase.enter(SessionPhase::Countdown)?;
+        async_bus.countdown(format!("Los geht's, {}!", name), COUNTDOWN_SECONDS).await?;
+
+        phase.enter(SessionPhase::Driving)?;
+        bus.send(VrMessage::TimerStart { name })?;
+        async_bus.wait_for(|m| matches!(m, VrMessage::TimerEnd {}), None).await?;
+
+        phase.enter(SessionPhase::Finished)?;
+        let entry = async_bus.wait_for(|m| matches!(m, VrMessage::PushTimerEntry {..}), Some(ENTRY_TIMEOUT)).await;
+        match entry {
+            Ok(VrMessage::PushTimerEntry { entry }) => Ok(Some(entry)),
+            _ => Ok(None),
+        }
+    }
+
+    /// A run timed from outside a session, it drives until the timer stops or it is aborted
+    async fn run_without_session(&mut self) -> Result<()> {
+        self.phase.enter(SessionPhase::Driving)?;
+        let end = self.async_bus.wait_for(
+            |m| matches!(m, VrMessage::TimerEnd {} | VrMessage::AbortSession {}),
+            None,
+        ).await?;
+        if let VrMessage::AbortSession {} = end {
+            self.phase.enter(SessionPhase::Aborted)?;
+        }
+        self.phase.enter(SessionPhase::Idle)
+    }
+
+    async fn show_results(&mut self, entry: Option<LeaderboardEntry>) -> Result<()> {
+        self.phase.enter(SessionPhase::Results)?;
+
+        if let Some(entry) = entry {
+            let rank = read_config().leaderboard
+                .iter()
+                .filter(|other| other.time < entry.time)
+                .count() + 1;
+            self.async_bus.toast(
+                format!("{}: {:.2} s, Platz {}", entry.name, entry.time, rank),
+                RESULTS_DURATION.as_millis() as u32,
+            ).await?;
+        }
+
+        tokio::time::sleep(RESULTS_DURATION).await;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl GameCoreUnit for Session {
+    /// Locks the throttle until something is driven
+    async fn init(&mut self) -> Result<()> {
+        self.phase.enter(SessionPhase::Idle)?;
+        self.repeater = Some(tokio::spawn(self.phase.clone().repeat()));
+        Ok(())
+    }
+
+    async fn process(&mut self) -> Result<()> {
+        let start = self.async_bus.wait_for(
+            |m| matches!(m, VrMessage::StartSession {} | VrMessage::TimerStart {..}),
+            None,
+        ).await?;
+        if let VrMessage::TimerStart { .. } = start {
+            return self.run_without_session().await;
+        }
+
+        // aborts sent while no session ran are stale
+        self.abort_bus.discard_pending();
+        let outcome = tokio::select! {
+            outcome = Self::run(&self.phase, &self.bus, &mut self.async_bus) => outcome,
+            aborted = self.abort_bus.wait_for(|m| matches!(m, VrMessage::AbortSession {}), None) => {
+                Err(aborted.err().unwrap_or(WaitError::Cancelled).into())
+            }
+        };
+
+        let result = match outcome {
+            Ok(entry) => self.show_results(entry).await,
+            Err(e) => {
+                self.async_bus.hide_interface().await?;
+                self.phase.enter(SessionPhase::Aborted)?;
+                tokio::time::sleep(Duration::from_secs(1)).await;
+                // the operator or the player ending a session early is part of the game, not a failure
+                match e.downcast_ref::<WaitError>() {
+                    Some(WaitError::Cancelled) => Ok(()),
+                    _ => Err(e),
+                }
+            }
+        };
+
+        self.phase.enter(SessionPhase::Idle)?;
+        result.context("Session failed")
+    }
+
+    /// Without the session unit nothing would ever unlock the throttle again
+    async fn shutdown(&mut self) -> Result<()> {
+        if let Some(repeater) = self.repeater.take() {
+            repeater.abort();
+        }
+        self.async_bus.hide_interface().await?;
+        self.phase.enter(SessionPhase::Off)
+    }
+}
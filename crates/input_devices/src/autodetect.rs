@@ -15,6 +15,7 @@ use crate::drivers::swarm::car::CarDriver;
 use crate::drivers::swarm::pedal::PedalDriver;
 use crate::drivers::swarm::steering_wheel::SteeringWheelDriver;
 use crate::drivers::swarm::VrSwarm;
+use crate::drivers::simulated::SimulatedDriver;
 use crate::InputDevices;
 
 #[derive(EnumIter, Debug)]
@@ -86,6 +87,19 @@ macro_rules! async_driver {
     };
 }
 
+/// No hardware at all, a single driver fakes the inputs
+pub fn simulated_input_devices(bus: &PubSub<VrMessage>) -> InputDevices {
+    let driver_bus = bus.clone();
+    InputDevices {
+        swarm: None,
+        drivers: vec![IdentifiedDeviceDriver {
+            driver: Some(sync_driver!(SimulatedDriver::new(driver_bus))),
+            name: "Simulated".to_string(),
+        }],
+        bus: bus.clone(),
+    }
+}
+
 pub async fn autodetect_input_devices(bus: &PubSub<VrMessage>) -> InputDevices {
     let mut drivers = AutodetectDeviceDriverList::new();
     let mut swarm: Option<VrSwarm> = None;
@@ -3,6 +3,7 @@ use crate::autodetect::BuildableDriver;
 
 pub mod swarm;
 pub mod headset;
+pub mod simulated;
 
 #[derive(Debug)]
 pub enum DriverProcessError {
@@ -15,6 +16,11 @@ pub enum DriverProcessError {
 #[async_trait::async_trait]
 pub trait DeviceDriver {
     async fn process(&mut self) -> Result<(), DriverProcessError>;
+
+    /// Leaves the hardware in a safe state before the program exits
+    async fn shutdown(&mut self) -> Result<(), DriverProcessError> {
+        Ok(())
+    }
 }
 
 pub struct IdentifiedDeviceDriver {
@@ -0,0 +1,49 @@
+use std::time::Instant;
+use async_trait::async_trait;
+use pub_sub::PubSub;
+use messages::VrMessage;
+use crate::drivers::{DeviceDriver, DriverProcessError};
+
+/// Stands in for the headset, the wheel and the pedal when there is no hardware: the head looks around
+/// slowly, the wheel steers back and forth and the pedal stays up.
+pub struct SimulatedDriver {
+    bus: PubSub<VrMessage>,
+    start: Instant,
+}
+
+impl SimulatedDriver {
+    pub fn new(bus: PubSub<VrMessage>) -> Box<dyn DeviceDriver> {
+        Box::new(SimulatedDriver {
+            bus,
+            start: Instant::now(),
+        })
+    }
+}
+
+#[async_trait]
+impl DeviceDriver for SimulatedDriver {
+    async fn process(&mut self) -> Result<(), DriverProcessError> {
+        let t = self.start.elapsed().as_secs_f32();
+
+        let messages = [
+            VrMessage::GyroscopeReading {
+                yaw: 0.5 * (t * 0.5).sin(),
+                pitch: 0.2 * (t * 0.3).sin(),
+                roll: 0.0,
+                temperature: 25.0,
+            },
+            VrMessage::WheelState {
+                rotation: (60.0 * (t * 0.4).sin()) as i128,
+                left_button: false,
+                right_button: false,
+                flipped: false,
+            },
+            VrMessage::PedalState { pressed: 0 },
+        ];
+        for message in messages {
+            self.bus.send(message).map_err(|_| DriverProcessError::BusError)?;
+        }
+
+        Ok(())
+    }
+}
@@ -2,13 +2,14 @@ use async_trait::async_trait;
 use ftswarm::prelude::{Io, Motor, Servo, SwarmObject};
 use log::info;
 use pub_sub::{PubSub, Subscription};
-use messages::file_config::{read_config, save_config};
-use messages::VrMessage;
+use messages::file_config::{read_config, update_config};
+use messages::{SessionPhase, UnitStatus, VrMessage};
 use crate::drivers::{DeviceDriver, DriverProcessError};
 use crate::drivers::swarm::{FtSwarmAliases, VrSwarm};
 
 pub struct CarDriver {
     pub(crate) swarm: VrSwarm,
+    bus: PubSub<VrMessage>,
     subscription: Subscription<VrMessage>,
     last_5_yaws: [f32; 5],
     last_5_pitches: [f32; 5],
@@ -22,6 +23,8 @@ pub struct CarDriver {
     old_offset_cam_yaw: i32,
     old_offset_cam_pitch: i32,
     interface_open: bool,
+    /// The session unit runs and nothing is being driven. Locked until the session unit says otherwise.
+    throttle_locked: bool,
     last_write: std::time::Instant,
     mapped_steer: i32,
     final_pitch: i32,
@@ -38,6 +41,7 @@ impl CarDriver {
     pub async fn new(swarm: VrSwarm, bus: PubSub<VrMessage>) -> Box<dyn DeviceDriver> {
         Box::new(CarDriver {
             subscription: bus.subscribe(),
+            bus,
             last_5_yaws: [0.0; 5],
             last_5_pitches: [0.0; 5],
             wheel_pos: 0,
@@ -50,6 +54,7 @@ impl CarDriver {
             old_offset_cam_yaw: 0,
             old_offset_cam_pitch: 0,
             interface_open: false,
+            throttle_locked: true,
             last_write: std::time::Instant::now(),
             mapped_steer: 0,
             final_pitch: 0,
@@ -98,10 +103,14 @@ impl DeviceDriver for CarDriver {
                     self.final_yaw = Self::remap(avg_yaw, -1.5, 1.5, -90.0, 90.0) as i32;
                     self.final_pitch = Self::remap(avg_pitch, -1.5, 1.5, -90.0, 90.0) as i32;
                 }
-                VrMessage::ShowRenderedInterface { .. } => {
-                    self.interface_open = true;
+                VrMessage::ShowRenderedInterface { interface } => {
+                    if interface.takes_input() {
+                        self.interface_open = true;
+                    }
                 }
-                VrMessage::InterfaceConfirm { .. } => {
+                VrMessage::InterfaceConfirm { .. }
+                | VrMessage::HideRenderedInterface {}
+                | VrMessage::CancelInterface {} => {
                     self.interface_open = false;
                 }
                 VrMessage::WheelState { rotation, left_button, right_button, .. } => {
@@ -115,13 +124,21 @@ impl DeviceDriver for CarDriver {
                         self.reverse = false;
                     }
                 }
+                VrMessage::SessionState { phase } => {
+                    self.throttle_locked = !matches!(phase, SessionPhase::Off | SessionPhase::Driving);
+                }
+                VrMessage::UnitStateUpdate { states } => {
+                    // a disabled session unit sends no phase at all, there is nothing to lock for
+                    if states.iter().any(|state| state.name == "session" && state.status == UnitStatus::Disabled) {
+                        self.throttle_locked = false;
+                    }
+                }
                 VrMessage::PedalState { pressed } => {
                     self.throttle = ((pressed as f32) * 1.5) as i32;
                 }
                 VrMessage::SetServoConfig { config } => {
-                    let mut fileconfig = read_config();
-                    fileconfig.servo_config = config;
-                    save_config(&fileconfig);
+                    update_config(|fileconfig| fileconfig.servo_config = config)
+                        .map_err(|_| DriverProcessError::IoError)?;
                 }
                 _ => {}
             }
@@ -130,6 +147,14 @@ impl DeviceDriver for CarDriver {
 
         let now = std::time::Instant::now();
         if now.duration_since(self.last_write).as_millis() > 100 &&!self.interface_open {
+            if self.last_pitch != self.final_pitch || self.last_yaw != self.final_yaw {
+                // the renderer reprojects the camera image by however far the servos are behind the head
+                let _ = self.bus.send(VrMessage::CameraServoPosition {
+                    yaw: Self::remap(self.final_yaw as f32, -90.0, 90.0, -1.5, 1.5),
+                    pitch: Self::remap(self.final_pitch as f32, -90.0, 90.0, -1.5, 1.5),
+                });
+            }
+
             if self.last_pitch != self.final_pitch {
                 self.last_pitch = self.final_pitch;
                 self.cam_pitch_servo.lock().await.set_position(self.final_pitch).await.map_err(|it| DriverProcessError::SwarmError("pitch_set".into(), it))?;
@@ -145,7 +170,9 @@ impl DeviceDriver for CarDriver {
                 self.steering_servo.lock().await.set_position(self.mapped_steer).await.map_err(|it| DriverProcessError::SwarmError("steer_set".into(), it))?;
             }
 
-            let final_throttle = if self.reverse {
+            let final_throttle = if self.throttle_locked {
+                0
+            } else if self.reverse {
                 -self.throttle
             } else {
                 self.throttle
@@ -181,4 +208,14 @@ impl DeviceDriver for CarDriver {
         }
         Ok(())
     }
+
+    /// Stops the motor and centers all servos, the car must not drive off on its own
+    async fn shutdown(&mut self) -> Result<(), DriverProcessError> {
+        info!("Stopping the car");
+        self.throttle_motor.lock().await.set(0).await.map_err(|it| DriverProcessError::SwarmError("throttle_set".into(), it))?;
+        self.steering_servo.lock().await.set_position(0).await.map_err(|it| DriverProcessError::SwarmError("steer_set".into(), it))?;
+        self.cam_yaw_servo.lock().await.set_position(0).await.map_err(|it| DriverProcessError::SwarmError("yaw_set".into(), it))?;
+        self.cam_pitch_servo.lock().await.set_position(0).await.map_err(|it| DriverProcessError::SwarmError("pitch_set".into(), it))?;
+        Ok(())
+    }
 }
\ No newline at end of file
@@ -2,7 +2,7 @@ use async_trait::async_trait;
 use ftswarm::prelude::{Io, SwarmObject, Ohmmeter, Hysteresis};
 use pub_sub::{PubSub, Subscription};
 use messages::{PedalPosition, VrMessage};
-use messages::file_config::{read_config, save_config};
+use messages::file_config::{read_config, update_config};
 use crate::drivers::{DeviceDriver, DriverProcessError};
 use crate::drivers::swarm::{FtSwarmAliases, VrSwarm};
 
@@ -52,15 +52,15 @@ impl DeviceDriver for PedalDriver {
                     match position {
                         PedalPosition::Lower => {
                             self.min = self.last_n.iter().sum::<i32>() / 5;
-                            let mut conf = read_config();
-                            conf.pedal_calibration_lower = self.min;
-                            save_config(&conf);
+                            let min = self.min;
+                            update_config(|conf| conf.pedal_calibration_lower = min)
+                                .map_err(|_| DriverProcessError::IoError)?;
                         }
                         PedalPosition::Upper => {
                             self.max = self.last_n.iter().sum::<i32>() / 5;
-                            let mut conf = read_config();
-                            conf.pedal_calibration_upper = self.max;
-                            save_config(&conf);
+                            let max = self.max;
+                            update_config(|conf| conf.pedal_calibration_upper = max)
+                                .map_err(|_| DriverProcessError::IoError)?;
                         }
                     }
                 }
@@ -32,6 +32,10 @@ impl InputDevices {
         autodetect::autodetect_input_devices(bus).await
     }
 
+    pub fn simulated(bus: &PubSub<VrMessage>) -> InputDevices {
+        autodetect::simulated_input_devices(bus)
+    }
+
     pub fn driver_states(&self) -> Vec<DriverState> {
         self.drivers.iter().map(|x| x.into()).collect()
     }
@@ -1,16 +1,36 @@
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, OnceLock};
 use ron::ser::PrettyConfig;
 use crate::{RenderSettingsData};
 
+static CONFIG_PATH: OnceLock<PathBuf> = OnceLock::new();
+/// Held while the config is written, so concurrent read-modify-writes don't undo each other
+static CONFIG_LOCK: Mutex<()> = Mutex::new(());
+static TEMPORARY_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+/// Uses another config than `conf.ron` in the working directory, only works before the config is first used
+pub fn set_config_path(path: impl Into<PathBuf>) {
+    let _ = CONFIG_PATH.set(path.into());
+}
+
+pub fn config_path() -> &'static Path {
+    CONFIG_PATH.get_or_init(|| PathBuf::from("conf.ron"))
+}
+
 fn write_default_config() {
     let conf: RenderSettingsData = Default::default();
     let string = ron::ser::to_string_pretty(&conf, Default::default()).unwrap();
-    std::fs::write("conf.ron", string).unwrap();
+    std::fs::write(config_path(), string).unwrap();
 }
 
 pub fn read_config() -> RenderSettingsData {
-    match std::fs::read_to_string("conf.ron") {
+    match std::fs::read_to_string(config_path()) {
         Ok(string) => {
-            ron::de::from_str(&string).unwrap()
+            let mut settings: RenderSettingsData = ron::de::from_str(&string).unwrap();
+            settings.migrate();
+            settings
         }
         Err(_) => {
             write_default_config();
@@ -19,7 +39,34 @@ pub fn read_config() -> RenderSettingsData {
     }
 }
 
-pub fn save_config(settings: &RenderSettingsData) {
-    let string = ron::ser::to_string_pretty(&settings, PrettyConfig::default()).unwrap();
-    std::fs::write("conf.ron", string).unwrap();
+/// Writes next to the config first and renames it over, so quitting mid-write never leaves half a config.
+/// Every write gets its own temporary file, the rename is what makes it visible.
+fn write_config(settings: &RenderSettingsData) -> io::Result<()> {
+    let string = ron::ser::to_string_pretty(&settings, PrettyConfig::default())
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    let mut temporary = config_path().as_os_str().to_owned();
+    temporary.push(format!(
+        ".{}.{}.tmp",
+        std::process::id(),
+        TEMPORARY_COUNTER.fetch_add(1, Ordering::Relaxed)
+    ));
+
+    std::fs::write(&temporary, string)?;
+    std::fs::rename(&temporary, config_path()).inspect_err(|_| {
+        let _ = std::fs::remove_file(&temporary);
+    })
+}
+
+pub fn save_config(settings: &RenderSettingsData) -> io::Result<()> {
+    let _guard = CONFIG_LOCK.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    write_config(settings)
+}
+
+/// Reads the config, lets `change` edit it and saves it, without another thread saving in between.
+/// Use this instead of saving a copy read earlier, which would revert everything changed since.
+pub fn update_config(change: impl FnOnce(&mut RenderSettingsData)) -> io::Result<()> {
+    let _guard = CONFIG_LOCK.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    let mut settings = read_config();
+    change(&mut settings);
+    write_config(&settings)
 }
@@ -1,3 +1,4 @@
+use std::collections::BTreeMap;
 use serde::{Deserialize, Serialize};
 
 pub mod file_config;
@@ -13,6 +14,36 @@ pub struct ServoConfig {
 pub struct EyeSettings {
     pub image_width: u32,
     pub image_height: u32,
+    #[serde(default)]
+    pub lens: LensDistortion,
+}
+
+/// Barrel pre-distortion that cancels out the pincushion distortion of the headset lens.
+/// Radii are measured from the lens center and normalized to half the shorter side of the eye's viewport.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LensDistortion {
+    pub k1: f32,
+    pub k2: f32,
+    /// Shifts the lens center in fractions of the eye's viewport, (0.5, 0.5) is right on the lens axis
+    /// that follows from `display.ipd_mm`
+    pub center: (f32, f32),
+    /// Zooms the distorted image so it fills the viewport again
+    pub scale: f32,
+    /// Per channel (red, green, blue) radius scale, the lens refracts red less than blue
+    pub chromatic_aberration: (f32, f32, f32),
+}
+
+impl Default for LensDistortion {
+    fn default() -> Self {
+        LensDistortion {
+            k1: 0.0,
+            k2: 0.0,
+            center: (0.5, 0.5),
+            scale: 1.0,
+            chromatic_aberration: (1.0, 1.0, 1.0),
+        }
+    }
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -26,12 +57,30 @@ pub struct LeaderboardEntry {
 pub struct RenderSettingsData {
     pub left_eye: EyeSettings,
     pub right_eye: EyeSettings,
+    /// Pixels in the fixed 800x480 window of configs before `layout_version` 1, only read to migrate them
+    #[serde(default, skip_serializing)]
     pub v_offset: i32,
+    #[serde(default, skip_serializing)]
     pub space_between_back: i32,
+    #[serde(default, skip_serializing)]
     pub space_between_front: i32,
-    #[serde(default)]
+    #[serde(default, skip_serializing)]
     pub space_between_ui: i32,
-    pub model: ModelType,
+    /// Moves the images up or down from the lens axes, in millimetres on the screen
+    #[serde(default)]
+    pub v_offset_mm: f32,
+    /// Moves the images outwards from the lens axes, in millimetres on the screen
+    #[serde(default)]
+    pub space_between_back_mm: f32,
+    #[serde(default)]
+    pub space_between_front_mm: f32,
+    #[serde(default)]
+    pub space_between_ui_mm: f32,
+    /// 0 for configs from before the layout was in millimetres, they are migrated when read
+    #[serde(default)]
+    pub layout_version: u32,
+    #[serde(default = "default_model_name")]
+    pub model_name: String,
     pub model_configuration: ModelConfiguration,
     pub servo_config: ServoConfig,
     #[serde(default)]
@@ -42,6 +91,57 @@ pub struct RenderSettingsData {
     pub speed_mul: f32,
     #[serde(default)]
     pub leaderboard: Vec<LeaderboardEntry>,
+    #[serde(default)]
+    pub mask_motion_compensation: f32,
+    #[serde(default)]
+    pub mask_smoothing: MaskSmoothing,
+    #[serde(default)]
+    pub mask_shaping: MaskShaping,
+    #[serde(default)]
+    pub display: DisplayGeometry,
+    #[serde(default)]
+    pub spectator: SpectatorConfig,
+    #[serde(default)]
+    pub reprojection: Reprojection,
+    #[serde(default)]
+    pub hud: HudLayout,
+    #[serde(default)]
+    pub access: AccessControl,
+    #[serde(default)]
+    pub units: UnitSettings,
+    #[serde(default)]
+    pub headless: HeadlessConfig,
+}
+
+/// Bumped when the meaning of the layout fields in `conf.ron` changes
+pub const LAYOUT_VERSION: u32 = 1;
+/// The window size the pixel layout of `layout_version` 0 was made for
+const LEGACY_WINDOW_WIDTH: f32 = 800.0;
+
+impl RenderSettingsData {
+    /// Brings configs from older layout versions up to date
+    pub fn migrate(&mut self) {
+        if self.layout_version < 1 {
+            self.migrate_pixel_layout();
+        }
+        self.layout_version = LAYOUT_VERSION;
+    }
+
+    /// Version 0 drew the images at their pixel size, with their inner edges `space_between_*` pixels from
+    /// the window center, and the UI centered on each half of the window. The same places in millimetres
+    /// from the lens axes keep a calibrated headset looking like it did.
+    fn migrate_pixel_layout(&mut self) {
+        let pixels_per_mm = self.display.pixels_per_mm();
+        let half_ipd = self.display.ipd_mm / 2.0;
+        let image_width = (self.left_eye.image_width + self.right_eye.image_width) as f32 / 2.0;
+        let space = |pixels: i32, center_distance: f32| (pixels as f32 + center_distance) / pixels_per_mm - half_ipd;
+
+        self.space_between_back_mm = space(self.space_between_back, image_width / 2.0);
+        self.space_between_front_mm = space(self.space_between_front, image_width / 2.0);
+        self.space_between_ui_mm = space(self.space_between_ui, LEGACY_WINDOW_WIDTH / 4.0);
+        self.v_offset_mm = self.v_offset as f32 / pixels_per_mm;
+        self.display.image_width_mm = Some(image_width / pixels_per_mm);
+    }
 }
 
 impl Default for RenderSettingsData {
@@ -50,20 +150,33 @@ impl Default for RenderSettingsData {
             left_eye: EyeSettings {
                 image_width: 400,
                 image_height: 480,
+                lens: LensDistortion::default(),
             },
             right_eye: EyeSettings {
                 image_width: 400,
                 image_height: 480,
+                lens: LensDistortion::default(),
             },
             v_offset: 0,
             space_between_back: 0,
             space_between_front: 0,
             space_between_ui: 0,
-            model: ModelType::YoloV8mInt8ONNX,
+            v_offset_mm: 0.0,
+            space_between_back_mm: 0.0,
+            space_between_front_mm: 0.0,
+            space_between_ui_mm: 0.0,
+            layout_version: LAYOUT_VERSION,
+            model_name: default_model_name(),
             model_configuration: ModelConfiguration {
                 confidence: 0.25,
                 iou: 0.7,
                 kconf: 0.55,
+                device: InferenceDevice::Auto,
+                cpu_threads: 0,
+                cpu_input_size: 320,
+                classes: default_mask_classes(),
+                segmenter: Segmenter::Yolo,
+                color_key: ColorKeyConfig::default(),
             },
             servo_config: ServoConfig {
                 steer_offset: 0,
@@ -74,21 +187,364 @@ impl Default for RenderSettingsData {
             pedal_calibration_upper: 0,
             speed_mul: 1.0,
             leaderboard: Vec::new(),
+            mask_motion_compensation: 0.0,
+            mask_smoothing: MaskSmoothing::default(),
+            mask_shaping: MaskShaping::default(),
+            display: DisplayGeometry::default(),
+            spectator: SpectatorConfig::default(),
+            reprojection: Reprojection::default(),
+            hud: HudLayout::default(),
+            access: AccessControl::default(),
+            units: UnitSettings::default(),
+            headless: HeadlessConfig::default(),
         }
     }
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize, Copy)]
-pub enum ModelType {
-    YoloV8mInt8ONNX,
-    YoloV8mHalfONNX,
-    YoloV8mFullONNX,
-    YoloV11sInt8ONNX,
-    YoloV11sHalfONNX,
-    YoloV11sFullONNX,
-    YoloV11mInt8ONNX,
-    YoloV11mHalfONNX,
-    YoloV11mFullONNX,
+/// Running without a window, see `vr_renderer::vr_headless_main`
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HeadlessConfig {
+    /// Keeps the cameras and hand segmentation running, the composed frames go to the spectator stream if it is enabled
+    pub segmentation: bool,
+    pub max_fps: u32,
+}
+
+impl Default for HeadlessConfig {
+    fn default() -> Self {
+        HeadlessConfig {
+            segmentation: false,
+            max_fps: 15,
+        }
+    }
+}
+
+/// How `game_core` runs its units
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct UnitSettings {
+    /// Units by name, the ones missing here are enabled
+    pub enabled: BTreeMap<String, bool>,
+    /// Wait after the first failed `process`, doubled for every further failure in a row
+    pub backoff_ms: u64,
+    pub max_backoff_ms: u64,
+    /// Failures in a row after which the unit is shut down and created again
+    pub restart_after: u32,
+}
+
+impl Default for UnitSettings {
+    fn default() -> Self {
+        UnitSettings {
+            enabled: BTreeMap::new(),
+            backoff_ms: 100,
+            max_backoff_ms: 10_000,
+            restart_after: 5,
+        }
+    }
+}
+
+impl UnitSettings {
+    pub fn is_enabled(&self, name: &str) -> bool {
+        self.enabled.get(name).copied().unwrap_or(true)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum UnitStatus {
+    Starting,
+    Running,
+    /// Failed too often, waiting to be created again
+    Restarting,
+    Disabled,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct UnitState {
+    pub name: String,
+    pub status: UnitStatus,
+    /// Failed `init` or `process` calls since the start
+    pub errors: u32,
+    pub restarts: u32,
+}
+
+/// Operator PIN for commands that change calibration or delete data, see `VrMessage::is_privileged`
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AccessControl {
+    /// `salt$sha256(salt + pin)` in hex, without a PIN every command is allowed
+    pub pin_hash: Option<String>,
+    /// Digits asked for when the PIN is entered in the headset
+    pub pin_length: u8,
+    /// Wrong PINs in a row before verification is locked
+    pub max_attempts: u32,
+    pub lockout_seconds: u64,
+    /// How long a verified PIN unlocks privileged commands
+    pub unlock_seconds: u64,
+    /// Every verification and privileged command is appended here
+    pub audit_log: String,
+}
+
+impl Default for AccessControl {
+    fn default() -> Self {
+        AccessControl {
+            pin_hash: None,
+            pin_length: 4,
+            max_attempts: 3,
+            lockout_seconds: 300,
+            unlock_seconds: 600,
+            audit_log: "audit.log".to_string(),
+        }
+    }
+}
+
+/// Physical layout of the headset. Eye placement is computed in millimetres from this, `v_offset_mm` and the
+/// `space_between_*_mm` values are millimetres as well.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DisplayGeometry {
+    /// Native resolution of the screen, also the size of the window
+    pub resolution: (u32, u32),
+    /// Screen diagonal, pixels are assumed to be square (like in screencalc.py)
+    pub diagonal_inches: f32,
+    /// Distance between the two lens centers
+    pub ipd_mm: f32,
+    /// Distance between the lenses and the screen
+    pub lens_distance_mm: f32,
+    /// Horizontal field of view of the cameras, the camera images are drawn so they cover the same angle
+    pub camera_fov_degrees: f32,
+    /// Drawn width of the camera images instead of the one that follows from `camera_fov_degrees`,
+    /// migrated configs use it to keep the size they were calibrated with
+    pub image_width_mm: Option<f32>,
+}
+
+impl DisplayGeometry {
+    /// Pixels are square, so this holds in both directions
+    pub fn pixels_per_mm(&self) -> f32 {
+        let (width, height) = (self.resolution.0.max(1) as f32, self.resolution.1.max(1) as f32);
+        width.hypot(height) / (self.diagonal_inches * 25.4)
+    }
+}
+
+impl Default for DisplayGeometry {
+    fn default() -> Self {
+        DisplayGeometry {
+            resolution: (800, 480),
+            diagonal_inches: 7.0,
+            ipd_mm: 63.0,
+            lens_distance_mm: 50.0,
+            camera_fov_degrees: 66.0,
+            image_width_mm: None,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum HudWidgetKind {
+    /// Throttle from the pedal, the car has no speed sensor
+    Speedometer,
+    RaceTimer,
+    /// Only filled by `TimerLap`, which nothing but the wizard's "Lap" button sends, so it is not in the default layout
+    LapSplits,
+    DriverStatus,
+    /// Warnings and errors from the log, and drivers that went offline
+    Warnings,
+}
+
+/// Widgets are placed relative to each eye's lens axis, in millimetres on the screen
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct HudWidget {
+    pub kind: HudWidgetKind,
+    pub position: (f32, f32),
+    /// Added to `space_between_ui_mm`, bigger values move the widget further away
+    #[serde(default)]
+    pub depth: f32,
+    #[serde(default = "default_hud_scale")]
+    pub scale: f32,
+}
+
+fn default_hud_scale() -> f32 {
+    1.0
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HudLayout {
+    pub enabled: bool,
+    pub widgets: Vec<HudWidget>,
+}
+
+impl Default for HudLayout {
+    fn default() -> Self {
+        let widget = |kind, position, depth| HudWidget {
+            kind,
+            position,
+            depth,
+            scale: 1.0,
+        };
+
+        HudLayout {
+            enabled: true,
+            widgets: vec![
+                widget(HudWidgetKind::RaceTimer, (-8.0, -30.0), 0.0),
+                widget(HudWidgetKind::Speedometer, (-12.0, 22.0), 1.0),
+                widget(HudWidgetKind::DriverStatus, (-28.0, -30.0), 0.5),
+                widget(HudWidgetKind::Warnings, (-20.0, 10.0), -0.5),
+            ],
+        }
+    }
+}
+
+/// Shifts the car camera images against the head movement the camera servos did not catch up with yet
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Reprojection {
+    pub enabled: bool,
+    /// 1 cancels out the estimated error completely
+    pub strength: f32,
+    /// How fast the camera servos turn, in radians per second
+    pub servo_speed: f32,
+}
+
+impl Default for Reprojection {
+    fn default() -> Self {
+        Reprojection {
+            enabled: false,
+            strength: 1.0,
+            servo_speed: 10.0,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub enum SpectatorView {
+    #[default]
+    Left,
+    Right,
+    Both,
+}
+
+/// MJPEG stream of what the player sees, before the lens distortion, for a screen at the booth
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SpectatorConfig {
+    pub enabled: bool,
+    pub view: SpectatorView,
+    pub address: String,
+    pub max_fps: u32,
+    /// Width of the stream in pixels, the height follows from the eyes' aspect ratio
+    pub width: u32,
+    pub quality: u8,
+    /// Every client gets its own thread, more are turned away
+    pub max_clients: usize,
+}
+
+impl Default for SpectatorConfig {
+    fn default() -> Self {
+        SpectatorConfig {
+            enabled: false,
+            view: SpectatorView::Left,
+            address: "0.0.0.0:6343".to_string(),
+            max_fps: 15,
+            width: 640,
+            quality: 70,
+            max_clients: 4,
+        }
+    }
+}
+
+/// How the soft model output is turned into the alpha of the hand overlay
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MaskShaping {
+    /// Probability range around 0.5 over which the mask fades in, 0 gives a hard edge
+    pub softness: f32,
+    /// Grows the mask by this many pixels, negative values shrink it
+    pub dilation: i32,
+    /// Blur sigma of the mask edge in pixels, 0 disables it
+    pub feather: f32,
+}
+
+impl Default for MaskShaping {
+    fn default() -> Self {
+        MaskShaping {
+            softness: 0.2,
+            dilation: 0,
+            feather: 2.0,
+        }
+    }
+}
+
+/// How masks are carried over from one inference result to the next
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MaskSmoothing {
+    /// Weight of a new mask against the previous ones, 1 disables blending
+    pub blend: f32,
+    /// A pixel shows up once its opacity rises above `show` and disappears once it drops below
+    /// `hide`, both as a fraction of full opacity. In between it keeps whatever it was before.
+    pub show: f32,
+    pub hide: f32,
+    /// Follow the camera image between inference results instead of the gyroscope
+    pub optical_flow: bool,
+}
+
+impl Default for MaskSmoothing {
+    fn default() -> Self {
+        MaskSmoothing {
+            blend: 0.6,
+            show: 0.35,
+            hide: 0.15,
+            optical_flow: false,
+        }
+    }
+}
+
+fn default_model_name() -> String {
+    "yolo11s-seg.onnx".to_string()
+}
+
+/// Box is `(xmin, ymin, width, height)` in pixels of the image the model saw
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Detection {
+    pub class: String,
+    pub confidence: f32,
+    pub bbox: (f32, f32, f32, f32),
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct DetectionFrame {
+    pub width: u32,
+    pub height: u32,
+    pub detections: Vec<Detection>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ModelInfo {
+    pub name: String,
+    pub task: String,
+    pub classes: Vec<String>,
+    pub image_size: Option<(u32, u32)>,
+    /// Exported with dynamic height and width, only those models can run at `cpu_input_size`
+    #[serde(default)]
+    pub dynamic_input: bool,
+    pub checksum: String,
+    pub verified: Option<bool>,
+    pub error: Option<String>,
+}
+
+impl ModelInfo {
+    pub fn is_compatible(&self) -> bool {
+        self.error.is_none() && self.task == "segment" && self.verified != Some(false)
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, Copy, PartialEq, Default)]
+pub enum InferenceDevice {
+    #[default]
+    Auto,
+    TensorRT,
+    Cuda,
+    Cpu,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize, Copy)]
@@ -102,6 +558,90 @@ pub struct ModelConfiguration {
     pub confidence: f32,
     pub iou: f32,
     pub kconf: f32,
+    #[serde(default)]
+    pub device: InferenceDevice,
+    #[serde(default)]
+    pub cpu_threads: u16,
+    /// Side of the square input used on the CPU. Needs a model exported with dynamic height and width
+    /// (`dynamic=True`), models with a fixed input size ignore it.
+    #[serde(default = "default_cpu_input_size")]
+    pub cpu_input_size: u32,
+    #[serde(default = "default_mask_classes")]
+    pub classes: Vec<MaskClass>,
+    #[serde(default)]
+    pub segmenter: Segmenter,
+    #[serde(default)]
+    pub color_key: ColorKeyConfig,
+}
+
+/// Which model masks the hands
+#[derive(Clone, Debug, Serialize, Deserialize, Copy, PartialEq, Default)]
+pub enum Segmenter {
+    /// YOLO on the GPU, otherwise the color key. A YOLO that fails to load only falls back while no model is running yet.
+    Auto,
+    #[default]
+    Yolo,
+    ColorKey,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, Copy, PartialEq, Default)]
+pub enum ColorSpace {
+    #[default]
+    YCrCb,
+    Hsv,
+}
+
+/// Masks pixels by color, skin tones by default. With `invert` it becomes a chroma key that
+/// masks everything except the key color.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ColorKeyConfig {
+    pub color_space: ColorSpace,
+    /// Inclusive `(min, max)` per channel, Y/Cr/Cb in 0..255 or hue in degrees and
+    /// saturation/value in 0..1. A hue range with min above max wraps around red.
+    pub ranges: [(f32, f32); 3],
+    pub invert: bool,
+    /// Radius of the opening and closing that remove speckles and fill holes
+    pub cleanup: u8,
+    /// Frames are keyed at this width, the mask is scaled back up afterwards
+    pub width: u32,
+}
+
+impl Default for ColorKeyConfig {
+    fn default() -> Self {
+        ColorKeyConfig {
+            color_space: ColorSpace::YCrCb,
+            ranges: [(0.0, 255.0), (133.0, 173.0), (77.0, 127.0)],
+            invert: false,
+            cleanup: 2,
+            width: 320,
+        }
+    }
+}
+
+fn default_cpu_input_size() -> u32 {
+    320
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct MaskClass {
+    pub name: String,
+    #[serde(default)]
+    pub confidence: Option<f32>,
+    #[serde(default = "default_mask_opacity")]
+    pub opacity: f32,
+}
+
+fn default_mask_opacity() -> f32 {
+    1.0
+}
+
+// The steering wheel is detected as a clock by the COCO models
+fn default_mask_classes() -> Vec<MaskClass> {
+    vec![
+        MaskClass { name: "person".to_string(), confidence: None, opacity: 1.0 },
+        MaskClass { name: "clock".to_string(), confidence: None, opacity: 1.0 },
+    ]
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize, Copy)]
@@ -122,11 +662,81 @@ pub enum DriverState {
     },
 }
 
+/// Screens shown inside the headset. The wheel moves the selection, either wheel button confirms.
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub enum Interface {
+    /// Picks a digit, answered with `InterfaceResponse::Number`
     InputNumberAndConfirm {
         text: String,
     },
+    /// Answered with the index of the chosen option
+    Menu {
+        title: String,
+        options: Vec<String>,
+    },
+    /// Yes/no question, answered with `InterfaceResponse::Confirmed`
+    Confirm {
+        question: String,
+    },
+    /// On-screen keyboard, answered with the entered text
+    TextEntry {
+        prompt: String,
+        max_length: u8,
+    },
+    /// Stays up until it is replaced or hidden, send it again to move the bar
+    Progress {
+        text: String,
+        progress: f32,
+    },
+    /// Counts down and answers with `InterfaceResponse::Done` at zero
+    Countdown {
+        text: String,
+        seconds: u32,
+    },
+    /// Shown for a while on top of everything else, never answered
+    Toast {
+        text: String,
+        duration_ms: u32,
+    },
+}
+
+impl Interface {
+    /// Whether the wheel belongs to this interface while it is open
+    pub fn takes_input(&self) -> bool {
+        matches!(
+            self,
+            Interface::InputNumberAndConfirm { .. }
+                | Interface::Menu { .. }
+                | Interface::Confirm { .. }
+                | Interface::TextEntry { .. }
+        )
+    }
+}
+
+/// Where a parkour session is, the session unit sends it on every change and once a second.
+/// The car only takes throttle while `Driving` or `Off`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub enum SessionPhase {
+    /// The session unit is not running, the car drives freely
+    Off,
+    #[default]
+    Idle,
+    PlayerSetup,
+    CalibrationCheck,
+    Countdown,
+    Driving,
+    Finished,
+    Aborted,
+    Results,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum InterfaceResponse {
+    Number(i32),
+    Option(usize),
+    Confirmed(bool),
+    Text(String),
+    Done,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -138,22 +748,35 @@ pub enum VrMessage {
         temperature: f32,
     },
     SetGyroscopeZero {},
+    /// All in millimetres, see the `*_mm` fields of `RenderSettingsData`
     VrDistanceConfiguration {
-        distance_between_b: i32,
-        distance_between_f: i32,
-        distance_between_u: i32,
-        v_offset: i32,
+        distance_between_b: f32,
+        distance_between_f: f32,
+        distance_between_u: f32,
+        v_offset: f32,
+    },
+    LensConfiguration {
+        left: LensDistortion,
+        right: LensDistortion,
     },
     ModelConfiguration {
-        model: ModelType,
+        model: String,
         config: ModelConfiguration,
     },
+    ListModels {},
+    ModelList {
+        models: Vec<ModelInfo>,
+    },
+    Detections {
+        frames: Vec<DetectionFrame>,
+    },
     Log {
         message: String,
         message_type: LogMessageType,
     },
+    /// Boxed, the whole config would make every message this large
     PushRenderSettings {
-        data: RenderSettingsData,
+        data: Box<RenderSettingsData>,
     },
     WheelState {
         rotation: i128,
@@ -164,6 +787,16 @@ pub enum VrMessage {
     DriverStateUpdate {
         states: Vec<DriverState>
     },
+    UnitStateUpdate {
+        states: Vec<UnitState>
+    },
+    /// Everything stops: the car halts, units shut down, websocket clients are closed and the window exits
+    Shutdown {},
+    /// Starts or stops a `game_core` unit and remembers it in the config
+    SetUnitEnabled {
+        name: String,
+        enabled: bool,
+    },
     FPSUpdate {
         fps: f32,
     },
@@ -180,8 +813,11 @@ pub enum VrMessage {
     ShowRenderedInterface {
         interface: Interface
     },
+    HideRenderedInterface {},
+    /// Aborts whatever the game is waiting for the player to answer
+    CancelInterface {},
     InterfaceConfirm {
-        data: i32
+        data: InterfaceResponse
     },
     AskPin {
         length: u8,
@@ -192,14 +828,83 @@ pub enum VrMessage {
     SetServoConfig {
         config: ServoConfig,
     },
+    /// Angles the camera servos were last told to turn to, in the gyroscope's radians
+    CameraServoPosition {
+        yaw: f32,
+        pitch: f32,
+    },
+    SetReprojection {
+        config: Reprojection,
+    },
+    SetMaskSmoothing {
+        config: MaskSmoothing,
+    },
     TimerStart {
         name: String,
     },
+    /// Has the player enter their name in the headset, then starts the timer with it
+    AskPlayerName {},
+    /// Runs a whole session: name entry, calibration, countdown, the timed run and the results
+    StartSession {},
+    AbortSession {},
+    SessionState {
+        phase: SessionPhase,
+    },
     TimerEnd {},
+    /// Marks a lap of the running timer
+    TimerLap {},
     PushTimerEntry {
         entry: LeaderboardEntry
     },
     DeleteTimerEntry {
         id: u32
     },
+    /// A privileged command, only passed on to the bus while the operator PIN is verified
+    Privileged {
+        command: Box<VrMessage>,
+    },
+    /// Asks for the operator PIN in the headset
+    Unlock {},
+    Lock {},
+    /// Like `AskPin`, but answered with `VerifyOperatorPin`, so other PIN prompts never count as operator attempts
+    AskOperatorPin {
+        length: u8,
+    },
+    /// An attempt at the operator PIN, from the wizard or the headset
+    VerifyOperatorPin {
+        pin: String,
+    },
+    /// Replaces the operator PIN, privileged itself
+    SetPin {
+        pin: String,
+    },
+    AccessState {
+        unlocked: bool,
+        /// Seconds until verification is possible again after too many wrong PINs
+        locked_out_for: u64,
+        pin_configured: bool,
+    },
+}
+
+impl VrMessage {
+    /// Commands that change calibration or delete data, they have to be wrapped in `Privileged`
+    pub fn is_privileged(&self) -> bool {
+        matches!(
+            self,
+            VrMessage::DeleteTimerEntry { .. }
+                | VrMessage::SetServoConfig { .. }
+                | VrMessage::ModelConfiguration { .. }
+                | VrMessage::ZeroPedal { .. }
+                | VrMessage::SetPin { .. }
+        )
+    }
+
+    /// Messages with a PIN in plain text, they must not leave the process or end up on disk
+    pub fn contains_pin(&self) -> bool {
+        match self {
+            VrMessage::ConfirmPin { .. } | VrMessage::VerifyOperatorPin { .. } | VrMessage::SetPin { .. } => true,
+            VrMessage::Privileged { command } => command.contains_pin(),
+            _ => false,
+        }
+    }
 }
\ No newline at end of file
@@ -0,0 +1,59 @@
+use std::path::PathBuf;
+use std::process::exit;
+use clap::Parser;
+use log::{error, info, LevelFilter};
+use messages::file_config::read_config;
+use vr_renderer::benchmark::{benchmark, candidates, label, load_dataset, table};
+
+/// Compares the segmentation models in ./onnx on labelled images, with the settings from ./conf.ron
+#[derive(Parser, Debug)]
+#[command(name = "segmentation_bench")]
+struct Cli {
+    /// Contains images/ and masks/, a mask shares the file stem of its image and is white where the hand is
+    #[arg(value_name = "DATASET")]
+    dataset: PathBuf,
+
+    /// How often every image is segmented, accuracy is only taken from the first run
+    #[arg(default_value_t = 5)]
+    runs: usize,
+
+    /// Also writes the Markdown table to FILE
+    #[arg(long, short, value_name = "FILE")]
+    output: Option<PathBuf>,
+}
+
+fn main() {
+    let Cli { dataset, runs, output } = Cli::parse();
+
+    pretty_env_logger::formatted_timed_builder()
+        .parse_default_env()
+        .filter_level(LevelFilter::Info)
+        .init();
+
+    let samples = match load_dataset(&dataset) {
+        Ok(samples) => samples,
+        Err(e) => {
+            error!("{:#}", e);
+            exit(1);
+        }
+    };
+    info!("Loaded {} labelled images from {}", samples.len(), dataset.display());
+
+    let settings = read_config();
+    let reports = candidates(&settings)
+        .iter()
+        .map(|candidate| {
+            info!("Benchmarking {}", label(candidate));
+            benchmark(candidate, &samples, runs)
+        })
+        .collect::<Vec<_>>();
+
+    let table = table(&reports);
+    println!("{}", table);
+    if let Some(output) = output {
+        if let Err(e) = std::fs::write(&output, &table) {
+            error!("Failed to write {}: {}", output.display(), e);
+            exit(1);
+        }
+    }
+}
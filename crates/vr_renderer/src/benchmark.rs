@@ -0,0 +1,251 @@
+use std::fs;
+use std::path::Path;
+use std::time::{Duration, Instant};
+use anyhow::{bail, Context, Result};
+use image::{DynamicImage, GrayImage};
+use log::warn;
+use messages::{MaskSmoothing, RenderSettingsData, Segmenter};
+use crate::models::load_model;
+use crate::models::registry::{ModelRegistry, MODEL_DIRECTORY};
+use crate::segmentation::SegmentationCache;
+
+/// A frame and its ground truth, white pixels in the mask are hand
+pub struct Sample {
+    pub name: String,
+    pub image: DynamicImage,
+    pub truth: GrayImage,
+}
+
+pub struct ModelReport {
+    pub name: String,
+    pub provider: String,
+    pub input_size: (u32, u32),
+    pub preprocess: Vec<Duration>,
+    pub inference: Vec<Duration>,
+    pub postprocess: Vec<Duration>,
+    pub total: Vec<Duration>,
+    pub iou: Vec<f32>,
+    pub error: Option<String>,
+}
+
+impl ModelReport {
+    fn failed(name: String, error: String) -> Self {
+        Self {
+            name,
+            provider: String::new(),
+            input_size: (0, 0),
+            preprocess: vec![],
+            inference: vec![],
+            postprocess: vec![],
+            total: vec![],
+            iou: vec![],
+            error: Some(error),
+        }
+    }
+
+    pub fn mean_iou(&self) -> f32 {
+        if self.iou.is_empty() {
+            return 0.0;
+        }
+        self.iou.iter().sum::<f32>() / self.iou.len() as f32
+    }
+}
+
+/// Reads `<dataset>/images/*` and the mask with the same file stem from `<dataset>/masks/`
+pub fn load_dataset(dataset: &Path) -> Result<Vec<Sample>> {
+    let images = dataset.join("images");
+    let masks = dataset.join("masks");
+    let mut samples = vec![];
+
+    let mut entries = fs::read_dir(&images)
+        .with_context(|| format!("Failed to read {}", images.display()))?
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.is_file())
+        .collect::<Vec<_>>();
+    entries.sort();
+
+    for path in entries {
+        let Some(stem) = path.file_stem().and_then(|it| it.to_str()) else {
+            continue;
+        };
+        let Some(truth_path) = ["png", "jpg", "jpeg", "bmp"]
+            .iter()
+            .map(|extension| masks.join(format!("{}.{}", stem, extension)))
+            .find(|it| it.is_file()) else {
+            warn!("No mask for {}, skipping it", path.display());
+            continue;
+        };
+
+        let image = image::open(&path)
+            .with_context(|| format!("Failed to open {}", path.display()))?;
+        let truth = image::open(&truth_path)
+            .with_context(|| format!("Failed to open {}", truth_path.display()))?
+            .resize_exact(image.width(), image.height(), image::imageops::FilterType::Nearest)
+            .to_luma8();
+
+        samples.push(Sample {
+            name: stem.to_string(),
+            image,
+            truth,
+        });
+    }
+
+    if samples.is_empty() {
+        bail!("No labelled images in {}", dataset.display());
+    }
+    Ok(samples)
+}
+
+/// Every compatible model in the model directory, plus the color key
+pub fn candidates(settings: &RenderSettingsData) -> Vec<RenderSettingsData> {
+    let mut registry = ModelRegistry::new(MODEL_DIRECTORY);
+    registry.refresh();
+
+    let mut candidates = registry.models()
+        .into_iter()
+        .filter(|model| model.is_compatible())
+        .map(|model| {
+            let mut candidate = settings.clone();
+            candidate.model_name = model.name;
+            candidate.model_configuration.segmenter = Segmenter::Yolo;
+            candidate
+        })
+        .collect::<Vec<_>>();
+
+    let mut color_key = settings.clone();
+    color_key.model_configuration.segmenter = Segmenter::ColorKey;
+    candidates.push(color_key);
+    candidates
+}
+
+/// What a candidate is called in the log and the table, the color key has no model file
+pub fn label(candidate: &RenderSettingsData) -> String {
+    match candidate.model_configuration.segmenter {
+        Segmenter::ColorKey => "color key".to_string(),
+        _ => candidate.model_name.clone(),
+    }
+}
+
+/// Runs every sample through the same `SegmentationCache` the renderer uses, `runs` times. Frames
+/// are paired up like the two eyes are, smoothing is turned off since the samples are unrelated.
+pub fn benchmark(settings: &RenderSettingsData, samples: &[Sample], runs: usize) -> ModelReport {
+    let name = label(settings);
+
+    let mut settings = settings.clone();
+    settings.mask_smoothing = MaskSmoothing {
+        blend: 1.0,
+        show: 0.0,
+        hide: 0.0,
+        optical_flow: false,
+    };
+
+    let mut model = match load_model(&settings, false) {
+        Ok(model) => model,
+        Err(e) => return ModelReport::failed(name, format!("{:#}", e)),
+    };
+    if let Err(e) = model.warm_up() {
+        return ModelReport::failed(name, format!("{:#}", e));
+    }
+
+    let mut cache = SegmentationCache::new(&settings);
+    cache.swap(model, &settings.model_configuration);
+
+    let mut report = ModelReport {
+        name: cache.model_name(),
+        provider: cache.execution_provider(),
+        input_size: cache.input_size(),
+        ..ModelReport::failed(name, String::new())
+    };
+    report.error = None;
+
+    for run in 0..runs.max(1) {
+        for pair in samples.chunks(2) {
+            // odd sample counts repeat the last frame, the model expects a full batch
+            let images = vec![pair[0].image.clone(), pair[pair.len() - 1].image.clone()];
+
+            let started = Instant::now();
+            let masks = cache.masks(&images);
+            let total = started.elapsed();
+            let timings = cache.timings();
+
+            report.preprocess.push(timings.preprocess);
+            report.inference.push(timings.inference);
+            report.postprocess.push(timings.postprocess);
+            report.total.push(total);
+
+            // accuracy does not change between runs
+            if run == 0 {
+                for (sample, mask) in pair.iter().zip(masks.iter()) {
+                    report.iou.push(iou(mask, &sample.truth));
+                }
+            }
+        }
+    }
+
+    report
+}
+
+/// Masks count as hand from half opacity on
+pub fn iou(mask: &GrayImage, truth: &GrayImage) -> f32 {
+    if mask.dimensions() != truth.dimensions() {
+        return 0.0;
+    }
+
+    let (mut intersection, mut union) = (0u64, 0u64);
+    for (predicted, expected) in mask.pixels().zip(truth.pixels()) {
+        let (predicted, expected) = (predicted.0[0] >= 128, expected.0[0] >= 128);
+        intersection += (predicted && expected) as u64;
+        union += (predicted || expected) as u64;
+    }
+
+    if union == 0 {
+        1.0
+    } else {
+        intersection as f32 / union as f32
+    }
+}
+
+pub fn percentile(durations: &[Duration], percentile: f32) -> Duration {
+    if durations.is_empty() {
+        return Duration::ZERO;
+    }
+    let mut sorted = durations.to_vec();
+    sorted.sort();
+    let index = ((percentile / 100.0) * (sorted.len() - 1) as f32).round() as usize;
+    sorted[index.min(sorted.len() - 1)]
+}
+
+/// Markdown table, latencies are per batch of two frames in milliseconds as p50/p95
+pub fn table(reports: &[ModelReport]) -> String {
+    let ms = |durations: &[Duration]| format!(
+        "{:.1}/{:.1}",
+        percentile(durations, 50.0).as_secs_f32() * 1000.0,
+        percentile(durations, 95.0).as_secs_f32() * 1000.0,
+    );
+
+    let mut table = String::from(
+        "| Model | Provider | Input | Pre | Infer | Post | Total | Total p99 | mIoU |\n\
+         |---|---|---|---|---|---|---|---|---|\n"
+    );
+    for report in reports {
+        if let Some(error) = &report.error {
+            table.push_str(&format!("| {} | failed: {} | | | | | | | |\n", report.name, error));
+            continue;
+        }
+        table.push_str(&format!(
+            "| {} | {} | {}x{} | {} | {} | {} | {} | {:.1} | {:.3} |\n",
+            report.name,
+            report.provider,
+            report.input_size.0,
+            report.input_size.1,
+            ms(&report.preprocess),
+            ms(&report.inference),
+            ms(&report.postprocess),
+            ms(&report.total),
+            percentile(&report.total, 99.0).as_secs_f32() * 1000.0,
+            report.mean_iou(),
+        ));
+    }
+    table
+}
@@ -0,0 +1,196 @@
+use std::time::{Duration, Instant};
+use image::{imageops, DynamicImage};
+use log::info;
+use pub_sub::PubSub;
+use messages::file_config::update_config;
+use messages::{Interface, InterfaceResponse, LogMessageType, RenderSettingsData, Segmenter, VrMessage};
+use crate::image_loader::ImageLoader;
+use crate::image_post_processing::postprocess;
+use crate::inference::HeadOrientation;
+use crate::models::registry::{ModelRegistry, MODEL_DIRECTORY};
+use crate::spectator::Spectator;
+
+/// How often the bus is checked while nothing is rendered
+const IDLE_INTERVAL: Duration = Duration::from_millis(50);
+/// Entered whenever the game logic asks for text, usually the player's name
+const HEADLESS_NAME: &str = "Headless";
+
+/// The hands drawn over the car camera image like the renderer does, without a GPU
+fn compose(back: DynamicImage, front: DynamicImage) -> DynamicImage {
+    let mut composed = back.to_rgba8();
+    let front = front
+        .resize_exact(composed.width(), composed.height(), imageops::FilterType::Triangle)
+        .to_rgba8();
+    imageops::overlay(&mut composed, &front, 0, 0);
+    DynamicImage::ImageRgba8(composed)
+}
+
+/// Cameras and segmentation for the headless mode, only there if `headless.segmentation` is on
+struct Pipeline {
+    loader: ImageLoader,
+    spectator: Option<Spectator>,
+    orientation: HeadOrientation,
+    interval: Duration,
+    last_frame: Instant,
+}
+
+impl Pipeline {
+    fn new(settings: &RenderSettingsData, pub_sub: &PubSub<VrMessage>) -> Self {
+        Pipeline {
+            loader: ImageLoader::new(settings, pub_sub.clone()),
+            spectator: Spectator::new(&settings.spectator, pub_sub.clone()),
+            orientation: HeadOrientation::default(),
+            interval: Duration::from_secs_f32(1.0 / settings.headless.max_fps.max(1) as f32),
+            last_frame: Instant::now(),
+        }
+    }
+
+    fn until_next_frame(&self) -> Duration {
+        self.interval.saturating_sub(self.last_frame.elapsed())
+    }
+
+    fn frame(&mut self, settings: &RenderSettingsData) {
+        self.last_frame = Instant::now();
+
+        let ((lf, lb), (rf, rb)) = self.loader.images(self.orientation);
+        let left = compose(postprocess(lb, &settings.left_eye, false), postprocess(lf, &settings.left_eye, true));
+        let right = compose(postprocess(rb, &settings.right_eye, false), postprocess(rf, &settings.right_eye, true));
+
+        if let Some(spectator) = &mut self.spectator {
+            spectator.capture_images(&left, &right);
+        }
+    }
+}
+
+/// Nobody can answer the headset, so prompts get what a player taking every default would answer. Digits are
+/// left alone, they are PIN digits and guessing those only locks the operator out.
+fn auto_answer(interface: &Interface) -> Option<InterfaceResponse> {
+    match interface {
+        Interface::Countdown { .. } => Some(InterfaceResponse::Done),
+        Interface::Confirm { .. } => Some(InterfaceResponse::Confirmed(true)),
+        Interface::Menu { options, .. } if !options.is_empty() => Some(InterfaceResponse::Option(0)),
+        Interface::TextEntry { max_length, .. } => Some(InterfaceResponse::Text(
+            HEADLESS_NAME.chars().take(*max_length as usize).collect(),
+        )),
+        _ => None,
+    }
+}
+
+/// Saves `change` to the config file like the renderer does, failures go to the wizard
+fn save(pub_sub: &PubSub<VrMessage>, change: impl FnOnce(&mut RenderSettingsData)) {
+    if let Err(e) = update_config(change) {
+        let _ = pub_sub.send(VrMessage::Log {
+            message: format!("Failed to save the config: {}", e),
+            message_type: LogMessageType::Error,
+        });
+    }
+}
+
+/// Without segmentation nothing loads the model, so it is only checked to exist before it is saved
+fn save_unloaded_model(settings: &RenderSettingsData, pub_sub: &PubSub<VrMessage>) {
+    let verified = match settings.model_configuration.segmenter {
+        Segmenter::ColorKey => Ok(()),
+        _ => ModelRegistry::verify(MODEL_DIRECTORY, &settings.model_name).map(|_| ()),
+    };
+    // the rest of the config may have changed since it was read
+    let saved = verified.and_then(|_| update_config(|file_config| {
+        file_config.model_name = settings.model_name.clone();
+        file_config.model_configuration = settings.model_configuration.clone();
+    }).map_err(anyhow::Error::from));
+
+    if let Err(e) = saved {
+        let _ = pub_sub.send(VrMessage::Log {
+            message: format!("Not switching to model {}: {:#}", settings.model_name, e),
+            message_type: LogMessageType::Error,
+        });
+    }
+}
+
+/// Stands in for the window when there is none: keeps the bus side of the renderer alive (model list,
+/// model switching, saving settings, answering the game logic's prompts) and, with `headless.segmentation`, the cameras, the hand segmentation and the spectator
+/// stream. Returns after `VrMessage::Shutdown` and `on_quit`.
+pub fn run(pub_sub: PubSub<VrMessage>, mut settings: RenderSettingsData, on_quit: impl FnOnce()) {
+    let subscription = pub_sub.subscribe();
+    let mut pipeline = settings.headless.segmentation.then(|| Pipeline::new(&settings, &pub_sub));
+    info!("Running headless, segmentation {}", if pipeline.is_some() { "on" } else { "off" });
+
+    'running: loop {
+        while let Ok(message) = subscription.try_recv() {
+            match message {
+                VrMessage::Shutdown {} => break 'running,
+
+                VrMessage::ModelConfiguration { model, config } => {
+                    let _ = pub_sub.send(VrMessage::Log {
+                        message: format!("Switching model to {}", model),
+                        message_type: LogMessageType::Info,
+                    });
+
+                    settings.model_name = model;
+                    settings.model_configuration = config;
+                    match &mut pipeline {
+                        // saved by the inference worker once the model is loaded
+                        Some(pipeline) => pipeline.loader.reload(&settings),
+                        None => save_unloaded_model(&settings, &pub_sub),
+                    }
+                }
+
+                VrMessage::SetMaskSmoothing { config } => {
+                    if let Some(pipeline) = &mut pipeline {
+                        pipeline.loader.set_smoothing(&config);
+                    }
+                    save(&pub_sub, |file_config| file_config.mask_smoothing = config.clone());
+                    settings.mask_smoothing = config;
+                }
+
+                VrMessage::VrDistanceConfiguration { distance_between_b, distance_between_f, v_offset, distance_between_u } => {
+                    save(&pub_sub, |file_config| {
+                        file_config.space_between_back_mm = distance_between_b;
+                        file_config.space_between_front_mm = distance_between_f;
+                        file_config.space_between_ui_mm = distance_between_u;
+                        file_config.v_offset_mm = v_offset;
+                    });
+                    settings.space_between_back_mm = distance_between_b;
+                    settings.space_between_front_mm = distance_between_f;
+                    settings.space_between_ui_mm = distance_between_u;
+                    settings.v_offset_mm = v_offset;
+                }
+
+                VrMessage::LensConfiguration { left, right } => {
+                    save(&pub_sub, |file_config| {
+                        file_config.left_eye.lens = left.clone();
+                        file_config.right_eye.lens = right.clone();
+                    });
+                    settings.left_eye.lens = left;
+                    settings.right_eye.lens = right;
+                }
+
+                VrMessage::SetReprojection { config } => {
+                    save(&pub_sub, |file_config| file_config.reprojection = config.clone());
+                    settings.reprojection = config;
+                }
+
+                VrMessage::ShowRenderedInterface { interface } => {
+                    if let Some(data) = auto_answer(&interface) {
+                        let _ = pub_sub.send(VrMessage::InterfaceConfirm { data });
+                    }
+                }
+
+                VrMessage::GyroscopeReading { yaw, pitch, .. } => {
+                    if let Some(pipeline) = &mut pipeline {
+                        pipeline.orientation = HeadOrientation { yaw, pitch };
+                    }
+                }
+
+                _ => {}
+            }
+        }
+
+        match &mut pipeline {
+            Some(pipeline) if pipeline.until_next_frame().is_zero() => pipeline.frame(&settings),
+            Some(pipeline) => std::thread::sleep(pipeline.until_next_frame().min(IDLE_INTERVAL)),
+            None => std::thread::sleep(IDLE_INTERVAL),
+        }
+    }
+
+    on_quit();
+}
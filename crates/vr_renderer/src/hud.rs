@@ -0,0 +1,231 @@
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+use ggez::{Context, GameResult};
+use ggez::glam::Vec2;
+use ggez::graphics::{Canvas, Color, DrawMode, DrawParam, Mesh, Rect, Text};
+use messages::{DriverState, HudLayout, HudWidget, HudWidgetKind, LogMessageType, VrMessage};
+use crate::transform::Display;
+
+const WARNING_DURATION: Duration = Duration::from_secs(5);
+const MAX_WARNINGS: usize = 3;
+const MAX_SPLITS: usize = 5;
+/// Text height in millimetres at scale 1
+const TEXT_SIZE: f32 = 2.5;
+
+const BACKGROUND: Color = Color::new(0.0, 0.0, 0.0, 0.5);
+const ACCENT: Color = Color::new(0.38, 0.65, 0.98, 1.0);
+const ONLINE: Color = Color::new(0.29, 0.87, 0.5, 1.0);
+const OFFLINE: Color = Color::new(0.97, 0.44, 0.44, 1.0);
+const WARNING: Color = Color::new(0.98, 0.8, 0.08, 1.0);
+
+struct Run {
+    name: String,
+    start: Instant,
+    laps: Vec<Duration>,
+}
+
+/// Everything the widgets show, collected from the bus
+#[derive(Default)]
+struct HudState {
+    throttle: f32,
+    run: Option<Run>,
+    /// Splits of the last finished run stay visible until the next one starts
+    last_run: Option<(Duration, Vec<Duration>)>,
+    drivers: Vec<DriverState>,
+    warnings: VecDeque<(String, Instant)>,
+}
+
+impl HudState {
+    fn warn(&mut self, message: String) {
+        self.warnings.push_back((message, Instant::now()));
+        while self.warnings.len() > MAX_WARNINGS {
+            self.warnings.pop_front();
+        }
+    }
+}
+
+/// Widgets drawn on top of both eyes. Each eye draws the same layout around its own lens axis,
+/// moved outwards by the widget's depth, so the HUD floats at a distance instead of sticking to the screen.
+pub struct Hud {
+    layout: HudLayout,
+    state: HudState,
+}
+
+impl Hud {
+    pub fn new(layout: &HudLayout) -> Self {
+        Self {
+            layout: layout.clone(),
+            state: HudState::default(),
+        }
+    }
+
+    pub fn process(&mut self, message: &VrMessage) {
+        let state = &mut self.state;
+        match message {
+            VrMessage::PedalState { pressed } => {
+                state.throttle = *pressed as f32 / 100.0;
+            }
+            VrMessage::TimerStart { name } => {
+                state.run = Some(Run {
+                    name: name.clone(),
+                    start: Instant::now(),
+                    laps: vec![],
+                });
+                state.last_run = None;
+            }
+            VrMessage::TimerLap {} => {
+                if let Some(run) = &mut state.run {
+                    run.laps.push(run.start.elapsed());
+                }
+            }
+            VrMessage::TimerEnd {} => {
+                if let Some(run) = state.run.take() {
+                    state.last_run = Some((run.start.elapsed(), run.laps));
+                }
+            }
+            VrMessage::DriverStateUpdate { states } => {
+                for driver in states {
+                    let was_online = state.drivers.iter()
+                        .any(|it| matches!(it, DriverState::Online { name } if name == driver_name(driver)));
+                    if was_online && matches!(driver, DriverState::Offline { .. }) {
+                        state.warn(format!("{} offline", driver_name(driver)));
+                    }
+                }
+                state.drivers = states.clone();
+            }
+            VrMessage::Log { message, message_type: LogMessageType::Warning | LogMessageType::Error } => {
+                state.warn(message.clone());
+            }
+            _ => {}
+        }
+    }
+
+    pub fn draw(&mut self, ctx: &mut Context, canvas: &mut Canvas, display: &Display, left: bool, space_between_ui: f32) -> GameResult {
+        if !self.layout.enabled {
+            return Ok(());
+        }
+
+        self.state.warnings.retain(|(_, since)| since.elapsed() < WARNING_DURATION);
+
+        let eye_origin = if left { Vec2::ZERO } else { display.right_eye_origin() };
+        for widget in &self.layout.widgets {
+            let disparity = space_between_ui + widget.depth;
+            let offset = Vec2::new(if left { -disparity } else { disparity }, 0.0);
+            let origin = display.to_pixels(display.lens_axis(left) + offset + Vec2::from(widget.position)) - eye_origin;
+            let painter = Painter {
+                origin,
+                unit: display.pixels_per_mm * widget.scale,
+            };
+
+            draw_widget(ctx, canvas, &painter, widget, &self.state)?;
+        }
+
+        Ok(())
+    }
+}
+
+fn driver_name(state: &DriverState) -> &str {
+    match state {
+        DriverState::Online { name } | DriverState::Offline { name } => name,
+    }
+}
+
+fn format_duration(duration: Duration) -> String {
+    let seconds = duration.as_secs_f32();
+    format!("{}:{:05.2}", (seconds / 60.0) as u32, seconds % 60.0)
+}
+
+/// Draws in millimetres relative to the widget's origin
+struct Painter {
+    origin: Vec2,
+    unit: f32,
+}
+
+impl Painter {
+    fn at(&self, x: f32, y: f32) -> Vec2 {
+        self.origin + Vec2::new(x, y) * self.unit
+    }
+
+    fn text(&self, canvas: &mut Canvas, text: &str, x: f32, y: f32, color: Color) {
+        canvas.draw(
+            Text::new(text)
+                .set_font("Arial")
+                .set_scale(TEXT_SIZE * self.unit),
+            DrawParam::default().dest(self.at(x, y)).color(color),
+        );
+    }
+
+    fn rect(&self, ctx: &mut Context, canvas: &mut Canvas, x: f32, y: f32, w: f32, h: f32, color: Color) -> GameResult {
+        let position = self.at(x, y);
+        let bounds = Rect::new(position.x, position.y, w * self.unit, h * self.unit);
+        canvas.draw(&Mesh::new_rectangle(ctx, DrawMode::fill(), bounds, color)?, DrawParam::default());
+        Ok(())
+    }
+
+    fn dot(&self, ctx: &mut Context, canvas: &mut Canvas, x: f32, y: f32, radius: f32, color: Color) -> GameResult {
+        let center = self.at(x, y);
+        canvas.draw(
+            &Mesh::new_circle(ctx, DrawMode::fill(), center, radius * self.unit, 0.1, color)?,
+            DrawParam::default(),
+        );
+        Ok(())
+    }
+}
+
+fn draw_widget(ctx: &mut Context, canvas: &mut Canvas, painter: &Painter, widget: &HudWidget, state: &HudState) -> GameResult {
+    match widget.kind {
+        HudWidgetKind::Speedometer => {
+            let throttle = state.throttle.clamp(0.0, 1.0);
+            painter.rect(ctx, canvas, 0.0, 0.0, 24.0, 4.0, BACKGROUND)?;
+            if throttle > 0.0 {
+                painter.rect(ctx, canvas, 0.5, 0.5, 23.0 * throttle, 3.0, ACCENT)?;
+            }
+            painter.text(canvas, &format!("{:>3.0}%", throttle * 100.0), 25.0, 0.5, Color::WHITE);
+        }
+        HudWidgetKind::RaceTimer => {
+            match (&state.run, &state.last_run) {
+                (Some(run), _) => {
+                    painter.text(canvas, &run.name, 0.0, 0.0, ACCENT);
+                    painter.text(canvas, &format_duration(run.start.elapsed()), 0.0, TEXT_SIZE * 1.2, Color::WHITE);
+                }
+                (None, Some((time, _))) => {
+                    painter.text(canvas, &format_duration(*time), 0.0, TEXT_SIZE * 1.2, ONLINE);
+                }
+                (None, None) => {}
+            }
+        }
+        HudWidgetKind::LapSplits => {
+            let laps = match (&state.run, &state.last_run) {
+                (Some(run), _) => &run.laps,
+                (None, Some((_, laps))) => laps,
+                (None, None) => return Ok(()),
+            };
+
+            let first = laps.len().saturating_sub(MAX_SPLITS);
+            for (row, index) in (first..laps.len()).enumerate() {
+                let previous = if index == 0 { Duration::ZERO } else { laps[index - 1] };
+                let split = laps[index].saturating_sub(previous);
+                let y = TEXT_SIZE * 1.2 * (row as f32 + 1.0);
+                painter.text(canvas, &format!("L{} {}", index + 1, format_duration(split)), 0.0, y, Color::WHITE);
+            }
+        }
+        HudWidgetKind::DriverStatus => {
+            for (row, driver) in state.drivers.iter().enumerate() {
+                let y = TEXT_SIZE * 1.2 * row as f32;
+                let color = match driver {
+                    DriverState::Online { .. } => ONLINE,
+                    DriverState::Offline { .. } => OFFLINE,
+                };
+                painter.dot(ctx, canvas, TEXT_SIZE / 2.0, y + TEXT_SIZE / 2.0, TEXT_SIZE / 3.0, color)?;
+                painter.text(canvas, driver_name(driver), TEXT_SIZE * 1.2, y, Color::WHITE);
+            }
+        }
+        HudWidgetKind::Warnings => {
+            for (row, (warning, _)) in state.warnings.iter().enumerate() {
+                painter.text(canvas, &format!("! {}", warning), 0.0, TEXT_SIZE * 1.2 * row as f32, WARNING);
+            }
+        }
+    }
+
+    Ok(())
+}
@@ -1,14 +1,19 @@
 use crate::imgstream::{DynamicImageStream, ImageStream, StaticImageStream};
+use crate::inference::{HeadOrientation, InferenceWorker};
 use crate::segmentation::SegmentationCache;
+use crate::stabilizer::{flow_frame, flow_offset};
 use ggez::graphics::{Image, ImageFormat};
 use ggez::Context;
 use image::{DynamicImage, EncodableLayout};
-use messages::RenderSettingsData;
+use messages::{MaskSmoothing, RenderSettingsData, VrMessage};
+use pub_sub::PubSub;
 use std::fmt::{Debug, Formatter};
 use std::sync::Arc;
 
 pub struct ImageLoader {
-    cache: SegmentationCache,
+    worker: InferenceWorker,
+    mask_motion_compensation: f32,
+    optical_flow: bool,
     // lf: Box<dyn ImageStream>,
     lf: Box<Arc<DynamicImageStream>>,
     // lb: Box<dyn ImageStream>,
@@ -21,7 +26,12 @@ pub struct ImageLoader {
 
 impl ImageLoader {
     pub(crate) fn reload(&mut self, settings: &RenderSettingsData) {
-        self.cache.reload(settings);
+        self.worker.reload(settings);
+    }
+
+    pub(crate) fn set_smoothing(&mut self, settings: &MaskSmoothing) {
+        self.optical_flow = settings.optical_flow;
+        self.worker.set_smoothing(settings);
     }
 }
 
@@ -32,9 +42,11 @@ impl Debug for ImageLoader {
 }
 
 impl ImageLoader {
-    pub fn new() -> Self {
+    pub fn new(settings: &RenderSettingsData, bus: PubSub<VrMessage>) -> Self {
         Self {
-            cache: SegmentationCache::new(),
+            worker: InferenceWorker::new(settings, bus),
+            mask_motion_compensation: settings.mask_motion_compensation,
+            optical_flow: settings.mask_smoothing.optical_flow,
             // lf: StaticImageStream::new(include_bytes!("../local-images/segmentable/l/example-foreground.png")),
             lf: DynamicImageStream::new("http://172.16.16.173:81/stream"),
             lb: DynamicImageStream::new("http://172.16.16.192:81/stream"),
@@ -46,16 +58,44 @@ impl ImageLoader {
         }
     }
 
-    fn system_meets_requirements(&self) -> bool {
-        // check if cuda is available using ORT
-        self.cache.is_gpu()
+    /// Moves the last mask by however far the head turned since its frame was captured
+    fn mask_offset(&self, from: HeadOrientation, to: HeadOrientation) -> (i32, i32) {
+        (
+            (-(to.yaw - from.yaw) * self.mask_motion_compensation) as i32,
+            (-(to.pitch - from.pitch) * self.mask_motion_compensation) as i32,
+        )
     }
 
-    pub fn images(&mut self, re_segment: bool) -> ((DynamicImage, DynamicImage), (DynamicImage, DynamicImage)) {
-        let mut overlay = if self.system_meets_requirements() {
-            self.cache.segment(vec![&self.rf.image(), &self.lf.image()], re_segment)
-        } else {
-            vec![DynamicImage::new_rgba8(self.rf.image().width(), self.rf.image().height()); 2]
+    pub fn images(&mut self, orientation: HeadOrientation) -> ((DynamicImage, DynamicImage), (DynamicImage, DynamicImage)) {
+        // The model letterboxes the frames itself, masks come back in their original size
+        let hands = vec![
+            self.rf.image(),
+            self.lf.image(),
+        ];
+
+        self.worker.submit(hands.clone(), orientation);
+
+        let mut overlay = match self.worker.latest() {
+            Some(result) if result.masks.len() == hands.len() => {
+                let head_offset = self.mask_offset(result.orientation, orientation);
+                let masks = result.masks
+                    .iter()
+                    .zip(hands.iter())
+                    .enumerate()
+                    .map(|(id, (mask, hand))| {
+                        let offset = match result.frames.get(id) {
+                            Some(frame) if self.optical_flow => flow_offset(frame, &flow_frame(hand), mask.dimensions()),
+                            _ => head_offset,
+                        };
+                        SegmentationCache::shift_mask(mask, offset)
+                    })
+                    .collect::<Vec<_>>();
+                SegmentationCache::apply_masks(&hands, &masks)
+            }
+            _ => hands
+                .iter()
+                .map(|hand| DynamicImage::new_rgba8(hand.width(), hand.height()))
+                .collect()
         };
 
         (
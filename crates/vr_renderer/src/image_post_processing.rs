@@ -2,11 +2,17 @@ use image::DynamicImage;
 use messages::EyeSettings;
 
 pub fn postprocess(image: DynamicImage, eye_settings: &EyeSettings, fg: bool) -> DynamicImage {
+    // Nearest would turn the soft mask edge of the foreground back into stairs
+    let filter = if fg {
+        image::imageops::FilterType::Triangle
+    } else {
+        image::imageops::FilterType::Nearest
+    };
     let image = image
         .resize_to_fill(
             eye_settings.image_width,
             eye_settings.image_height,
-            image::imageops::FilterType::Nearest
+            filter
         );
 
     if !fg {
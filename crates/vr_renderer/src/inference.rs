@@ -0,0 +1,263 @@
+use std::fmt::{Debug, Formatter};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+use image::{DynamicImage, GrayImage};
+use log::{error, info, warn};
+use pub_sub::PubSub;
+use tracing::debug_span;
+use messages::file_config::update_config;
+use messages::{InferenceDevice, LogMessageType, MaskSmoothing, RenderSettingsData, Segmenter, VrMessage};
+use crate::models::{load_model, SegmentationModel};
+use crate::segmentation::SegmentationCache;
+use crate::stabilizer::flow_frame;
+
+/// Detections are a debugging aid, the wizard does not need them at inference rate
+const DETECTION_INTERVAL: Duration = Duration::from_millis(200);
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct HeadOrientation {
+    pub yaw: f32,
+    pub pitch: f32,
+}
+
+pub struct InferenceJob {
+    pub images: Vec<DynamicImage>,
+    pub orientation: HeadOrientation,
+}
+
+#[derive(Clone)]
+pub struct InferenceResult {
+    pub masks: Vec<GrayImage>,
+    pub orientation: HeadOrientation,
+    /// Flow frames of the images the masks were computed from, empty without optical flow
+    pub frames: Vec<GrayImage>,
+}
+
+struct LoadedModel {
+    generation: u64,
+    settings: RenderSettingsData,
+    model: Box<dyn SegmentationModel>,
+}
+
+#[derive(Default)]
+struct WorkerInbox {
+    job: Option<InferenceJob>,
+    reload: Option<RenderSettingsData>,
+    loaded: Option<LoadedModel>,
+    smoothing: Option<MaskSmoothing>,
+}
+
+/// Runs segmentation on its own thread. The render loop hands over the latest foreground
+/// frames and picks up whatever masks were finished last, so inference never blocks a frame.
+pub struct InferenceWorker {
+    inbox: Arc<(Mutex<WorkerInbox>, Condvar)>,
+    result: Arc<Mutex<Option<InferenceResult>>>,
+}
+
+impl Debug for InferenceWorker {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("InferenceWorker").finish()
+    }
+}
+
+impl InferenceWorker {
+    pub fn new(settings: &RenderSettingsData, bus: PubSub<VrMessage>) -> Self {
+        let inbox = Arc::new((Mutex::new(WorkerInbox::default()), Condvar::new()));
+        let result = Arc::new(Mutex::new(None));
+
+        let worker_inbox = Arc::clone(&inbox);
+        let worker_result = Arc::clone(&result);
+        let settings = settings.clone();
+
+        thread::Builder::new()
+            .name("inference".to_string())
+            .spawn(move || Self::run(settings, worker_inbox, worker_result, bus))
+            .expect("Failed to spawn inference thread");
+
+        Self {
+            inbox,
+            result,
+        }
+    }
+
+    /// Builds and warms up the model on its own thread, the worker keeps inferring with the
+    /// current model until the new one is handed over through the inbox
+    fn spawn_load(
+        generation: u64,
+        settings: RenderSettingsData,
+        current: Option<String>,
+        inbox: Arc<(Mutex<WorkerInbox>, Condvar)>,
+        bus: PubSub<VrMessage>,
+    ) {
+        let name = match settings.model_configuration.segmenter {
+            Segmenter::ColorKey => "color key".to_string(),
+            _ => settings.model_name.clone(),
+        };
+        let thread_name = name.clone();
+        let spawned = thread::Builder::new()
+            .name("model-loader".to_string())
+            .spawn(move || {
+                let started = Instant::now();
+                Self::log(&bus, format!("Loading model {}", name), LogMessageType::Info);
+
+                let loaded = load_model(&settings, current.is_none()).and_then(|mut model| {
+                    Self::log(&bus, format!("Warming up model {}", model.name()), LogMessageType::Info);
+                    debug_span!("inference.warm_up").in_scope(|| model.warm_up())?;
+                    Ok(model)
+                });
+
+                match loaded {
+                    Ok(model) => {
+                        info!("Model {} ready after {:.1}s", model.name(), started.elapsed().as_secs_f32());
+                        let (lock, condvar) = &*inbox;
+                        lock.lock().unwrap().loaded = Some(LoadedModel { generation, settings, model });
+                        condvar.notify_one();
+                    }
+                    Err(e) => {
+                        let keeping = match current {
+                            Some(current) => format!("keeping {}", current),
+                            None => "no model is loaded".to_string(),
+                        };
+                        Self::log(&bus, format!("Failed to load model {}, {}: {:#}", name, keeping, e), LogMessageType::Error);
+                    }
+                }
+            });
+
+        if let Err(e) = spawned {
+            error!("Failed to spawn loader thread for {}: {}", thread_name, e);
+        }
+    }
+
+    fn log(bus: &PubSub<VrMessage>, message: String, message_type: LogMessageType) {
+        match message_type {
+            LogMessageType::Error => error!("{}", message),
+            LogMessageType::Warning => warn!("{}", message),
+            _ => info!("{}", message),
+        }
+        let _ = bus.send(VrMessage::Log { message, message_type });
+    }
+
+    fn persist(settings: &RenderSettingsData, bus: &PubSub<VrMessage>) {
+        let saved = update_config(|config| {
+            config.model_name = settings.model_name.clone();
+            config.model_configuration = settings.model_configuration.clone();
+        });
+        if let Err(e) = saved {
+            Self::log(bus, format!("Failed to save model {}: {}", settings.model_name, e), LogMessageType::Error);
+        }
+    }
+
+    fn report_backend(cache: &SegmentationCache, settings: &RenderSettingsData, bus: &PubSub<VrMessage>) {
+        let (width, height) = cache.input_size();
+        let requested = settings.model_configuration.device;
+        let color_key = settings.model_configuration.segmenter == Segmenter::ColorKey;
+        let message = if requested != InferenceDevice::Cpu && !cache.is_gpu() && !color_key {
+            let message = format!("Switched to model {}, but {:?} is not available, inference falls back to {} at {}x{}", cache.model_name(), requested, cache.execution_provider(), width, height);
+            warn!("{}", message);
+            VrMessage::Log { message, message_type: LogMessageType::Warning }
+        } else {
+            let message = format!("Switched to model {}, running on {} at {}x{}", cache.model_name(), cache.execution_provider(), width, height);
+            info!("{}", message);
+            VrMessage::Log { message, message_type: LogMessageType::Info }
+        };
+
+        let _ = bus.send(message);
+    }
+
+    fn run(
+        settings: RenderSettingsData,
+        inbox: Arc<(Mutex<WorkerInbox>, Condvar)>,
+        result: Arc<Mutex<Option<InferenceResult>>>,
+        bus: PubSub<VrMessage>,
+    ) {
+        let mut cache = SegmentationCache::new(&settings);
+        let mut optical_flow = settings.mask_smoothing.optical_flow;
+        let mut detections_sent = Instant::now();
+        let mut current: Option<String> = None;
+        // Only the most recently requested model is swapped in, slower loads of older requests are dropped
+        let mut generation = 0;
+        Self::spawn_load(generation, settings, None, Arc::clone(&inbox), bus.clone());
+
+        let (lock, condvar) = &*inbox;
+        loop {
+            let (job, reload, loaded, smoothing) = {
+                let mut guard = lock.lock().unwrap();
+                while guard.job.is_none() && guard.reload.is_none() && guard.loaded.is_none() && guard.smoothing.is_none() {
+                    guard = condvar.wait(guard).unwrap();
+                }
+                (guard.job.take(), guard.reload.take(), guard.loaded.take(), guard.smoothing.take())
+            };
+
+            if let Some(smoothing) = smoothing {
+                optical_flow = smoothing.optical_flow;
+                cache.set_smoothing(&smoothing);
+            }
+
+            if let Some(settings) = reload {
+                generation += 1;
+                Self::spawn_load(generation, settings, current.clone(), Arc::clone(&inbox), bus.clone());
+            }
+
+            if let Some(loaded) = loaded {
+                if loaded.generation == generation {
+                    cache.swap(loaded.model, &loaded.settings.model_configuration);
+                    current = Some(cache.model_name());
+                    Self::report_backend(&cache, &loaded.settings, &bus);
+                    // the startup model came from the config, switches are only saved once they loaded
+                    if loaded.generation > 0 {
+                        Self::persist(&loaded.settings, &bus);
+                    }
+                } else {
+                    info!("Dropping model {}, a newer model was requested meanwhile", loaded.settings.model_name);
+                }
+            }
+
+            let Some(job) = job else {
+                continue;
+            };
+
+            let masks = debug_span!("inference.masks")
+                .in_scope(|| cache.masks(&job.images));
+            if detections_sent.elapsed() >= DETECTION_INTERVAL {
+                detections_sent = Instant::now();
+                let _ = bus.send(VrMessage::Detections { frames: cache.detections().clone() });
+            }
+
+            let frames = if optical_flow {
+                job.images.iter().map(flow_frame).collect()
+            } else {
+                vec![]
+            };
+
+            *result.lock().unwrap() = Some(InferenceResult {
+                masks,
+                orientation: job.orientation,
+                frames,
+            });
+        }
+    }
+
+    /// Replaces any frames the worker has not picked up yet, older frames are never worth inferring
+    pub fn submit(&self, images: Vec<DynamicImage>, orientation: HeadOrientation) {
+        let (lock, condvar) = &*self.inbox;
+        lock.lock().unwrap().job = Some(InferenceJob { images, orientation });
+        condvar.notify_one();
+    }
+
+    pub fn reload(&self, settings: &RenderSettingsData) {
+        let (lock, condvar) = &*self.inbox;
+        lock.lock().unwrap().reload = Some(settings.clone());
+        condvar.notify_one();
+    }
+
+    pub fn set_smoothing(&self, settings: &MaskSmoothing) {
+        let (lock, condvar) = &*self.inbox;
+        lock.lock().unwrap().smoothing = Some(settings.clone());
+        condvar.notify_one();
+    }
+
+    pub fn latest(&self) -> Option<InferenceResult> {
+        self.result.lock().unwrap().clone()
+    }
+}
@@ -0,0 +1,179 @@
+use std::time::{Duration, Instant};
+use messages::{Interface, InterfaceResponse};
+
+/// Wheel rotation per selection step
+const STEP: i128 = 20;
+const KEYBOARD: &str = "ABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789 -";
+const BACKSPACE: &str = "DEL";
+const DONE: &str = "OK";
+/// Keys shown left and right of the selected one
+const KEYBOARD_CONTEXT: usize = 3;
+
+/// A line of an interface screen, the selected entry is highlighted
+pub struct Line {
+    pub text: String,
+    pub selected: bool,
+}
+
+impl Line {
+    fn plain(text: impl Into<String>) -> Self {
+        Line { text: text.into(), selected: false }
+    }
+}
+
+/// An open interface and what the player did in it so far
+pub struct OpenInterface {
+    interface: Interface,
+    /// Wheel position when it was opened, menus start at their first entry
+    start_rotation: i128,
+    opened: Instant,
+    text: String,
+}
+
+impl OpenInterface {
+    pub fn new(interface: Interface, rotation: i128) -> Self {
+        Self {
+            interface,
+            start_rotation: rotation,
+            opened: Instant::now(),
+            text: String::new(),
+        }
+    }
+
+    fn selection(&self, rotation: i128, count: usize) -> usize {
+        ((rotation - self.start_rotation) / STEP).rem_euclid(count.max(1) as i128) as usize
+    }
+
+    fn keys(&self) -> Vec<String> {
+        let mut keys = KEYBOARD.chars().map(|key| key.to_string()).collect::<Vec<_>>();
+        keys.push(BACKSPACE.to_string());
+        keys.push(DONE.to_string());
+        keys
+    }
+
+    fn remaining(&self, seconds: u32) -> Duration {
+        Duration::from_secs(seconds as u64).saturating_sub(self.opened.elapsed())
+    }
+
+    /// A wheel button was pressed, `Some` closes the interface with that response
+    pub fn press(&mut self, rotation: i128) -> Option<InterfaceResponse> {
+        match &self.interface {
+            Interface::InputNumberAndConfirm { .. } => {
+                Some(InterfaceResponse::Number(((rotation / STEP) % 10).abs() as i32))
+            }
+            Interface::Menu { options, .. } => {
+                Some(InterfaceResponse::Option(self.selection(rotation, options.len())))
+            }
+            Interface::Confirm { .. } => {
+                Some(InterfaceResponse::Confirmed(self.selection(rotation, 2) == 0))
+            }
+            Interface::TextEntry { max_length, .. } => {
+                let keys = self.keys();
+                let key = &keys[self.selection(rotation, keys.len())];
+                match key.as_str() {
+                    DONE => return Some(InterfaceResponse::Text(self.text.trim().to_string())),
+                    BACKSPACE => {
+                        self.text.pop();
+                    }
+                    _ if self.text.chars().count() < *max_length as usize => self.text.push_str(key),
+                    _ => {}
+                }
+                None
+            }
+            Interface::Progress { .. } | Interface::Countdown { .. } | Interface::Toast { .. } => None,
+        }
+    }
+
+    /// Responses that come without input, like a countdown running out
+    pub fn tick(&self) -> Option<InterfaceResponse> {
+        match &self.interface {
+            Interface::Countdown { seconds, .. } if self.remaining(*seconds).is_zero() => Some(InterfaceResponse::Done),
+            _ => None,
+        }
+    }
+
+    pub fn lines(&self, rotation: i128) -> Vec<Line> {
+        let selectable = |options: &[String], selected: usize| options
+            .iter()
+            .enumerate()
+            .map(|(index, option)| Line {
+                text: option.clone(),
+                selected: index == selected,
+            })
+            .collect::<Vec<_>>();
+
+        match &self.interface {
+            Interface::InputNumberAndConfirm { text } => {
+                let chosen_number = ((rotation / STEP) % 10).abs();
+                vec![
+                    Line::plain(format!("{}{}", text, chosen_number)),
+                    Line::plain("<-> Rad | [x] Taster"),
+                ]
+            }
+            Interface::Menu { title, options } => {
+                let mut lines = vec![Line::plain(title.clone())];
+                lines.extend(selectable(options, self.selection(rotation, options.len())));
+                lines
+            }
+            Interface::Confirm { question } => {
+                let options = ["Ja".to_string(), "Nein".to_string()];
+                let mut lines = vec![Line::plain(question.clone())];
+                lines.extend(selectable(&options, self.selection(rotation, 2)));
+                lines
+            }
+            Interface::TextEntry { prompt, .. } => {
+                let keys = self.keys();
+                let selected = self.selection(rotation, keys.len());
+                let window = (0..KEYBOARD_CONTEXT * 2 + 1)
+                    .map(|offset| (selected + keys.len() + offset - KEYBOARD_CONTEXT) % keys.len())
+                    .map(|index| if index == selected {
+                        format!("[{}]", keys[index])
+                    } else {
+                        keys[index].clone()
+                    })
+                    .collect::<Vec<_>>()
+                    .join(" ");
+
+                vec![
+                    Line::plain(prompt.clone()),
+                    Line::plain(format!("{}_", self.text)),
+                    Line { text: window, selected: true },
+                ]
+            }
+            Interface::Progress { text, progress } => {
+                let filled = (progress.clamp(0.0, 1.0) * 20.0).round() as usize;
+                vec![
+                    Line::plain(text.clone()),
+                    Line::plain(format!("[{}{}] {:.0}%", "#".repeat(filled), ".".repeat(20 - filled), progress.clamp(0.0, 1.0) * 100.0)),
+                ]
+            }
+            Interface::Countdown { text, seconds } => {
+                vec![
+                    Line::plain(text.clone()),
+                    Line {
+                        text: format!("{}", self.remaining(*seconds).as_secs_f32().ceil() as u32),
+                        selected: true,
+                    },
+                ]
+            }
+            Interface::Toast { text, .. } => vec![Line::plain(text.clone())],
+        }
+    }
+}
+
+/// Short messages shown on top of whatever else is open
+#[derive(Default)]
+pub struct Toasts {
+    toasts: Vec<(String, Instant, Duration)>,
+}
+
+impl Toasts {
+    pub fn push(&mut self, text: String, duration_ms: u32) {
+        self.toasts.push((text, Instant::now(), Duration::from_millis(duration_ms as u64)));
+    }
+
+    pub fn lines(&mut self) -> Vec<Line> {
+        self.toasts.retain(|(_, shown, duration)| shown.elapsed() < *duration);
+        self.toasts.iter().map(|(text, _, _)| Line::plain(text.clone())).collect()
+    }
+}
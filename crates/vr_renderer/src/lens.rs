@@ -0,0 +1,75 @@
+use crevice::std140::AsStd140;
+use ggez::{Context, GameResult};
+use ggez::glam::Vec2;
+use ggez::graphics::{Canvas, DrawParam, Image, Shader, ShaderBuilder, ShaderParams, ShaderParamsBuilder};
+use messages::{EyeSettings, LensDistortion};
+use crate::transform::Display;
+
+#[derive(AsStd140)]
+struct LensUniforms {
+    k1: f32,
+    k2: f32,
+    center_x: f32,
+    center_y: f32,
+    aspect: f32,
+    scale: f32,
+    red: f32,
+    green: f32,
+    blue: f32,
+}
+
+impl LensUniforms {
+    fn new(lens: &LensDistortion, display: &Display, left: bool) -> Self {
+        let viewport = display.eye_viewport();
+        let axis = display.lens_center(left);
+        Self {
+            k1: lens.k1,
+            k2: lens.k2,
+            center_x: axis.0 + lens.center.0 - 0.5,
+            center_y: axis.1 + lens.center.1 - 0.5,
+            aspect: viewport.0 / viewport.1.max(1.0),
+            scale: if lens.scale > 0.0 { lens.scale } else { 1.0 },
+            red: lens.chromatic_aberration.0,
+            green: lens.chromatic_aberration.1,
+            blue: lens.chromatic_aberration.2,
+        }
+    }
+}
+
+/// Draws each eye's composited image through its lens distortion shader
+pub struct LensPass {
+    shader: Shader,
+    left: ShaderParams<LensUniforms>,
+    right: ShaderParams<LensUniforms>,
+}
+
+impl LensPass {
+    pub fn new(ctx: &mut Context, display: &Display, left: &EyeSettings, right: &EyeSettings) -> GameResult<Self> {
+        let shader = ShaderBuilder::new()
+            .fragment_code(include_str!("shaders/lens.wgsl"))
+            .build(&ctx.gfx)?;
+
+        Ok(Self {
+            shader,
+            left: ShaderParamsBuilder::new(&LensUniforms::new(&left.lens, display, true)).build(ctx),
+            right: ShaderParamsBuilder::new(&LensUniforms::new(&right.lens, display, false)).build(ctx),
+        })
+    }
+
+    pub fn update(&mut self, ctx: &mut Context, display: &Display, left: &EyeSettings, right: &EyeSettings) {
+        self.left.set_uniforms(ctx, &LensUniforms::new(&left.lens, display, true));
+        self.right.set_uniforms(ctx, &LensUniforms::new(&right.lens, display, false));
+    }
+
+    pub fn draw(&self, canvas: &mut Canvas, left: &Image, left_dest: Vec2, right: &Image, right_dest: Vec2) {
+        canvas.set_shader(&self.shader);
+
+        canvas.set_shader_params(&self.left);
+        canvas.draw(left, DrawParam::default().dest(left_dest));
+
+        canvas.set_shader_params(&self.right);
+        canvas.draw(right, DrawParam::default().dest(right_dest));
+
+        canvas.set_default_shader();
+    }
+}
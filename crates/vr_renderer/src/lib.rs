@@ -4,6 +4,15 @@ mod transform;
 mod segmentation;
 mod models;
 mod imgstream;
+mod inference;
+mod stabilizer;
+mod lens;
+mod spectator;
+mod reprojection;
+mod hud;
+mod interface;
+mod headless;
+pub mod benchmark;
 
 use std::fmt::{format, Debug, Formatter};
 use std::time::Instant;
@@ -11,16 +20,23 @@ use ggez::{Context, GameResult};
 use ggez::conf::{FullscreenType, WindowMode, WindowSetup};
 use ggez::event::{EventHandler, EventLoop};
 use ggez::glam::Vec2;
-use ggez::graphics::{self, Color, DrawParam, Image, Transform};
+use ggez::graphics::{self, Canvas, Color, DrawParam, Image, Transform};
 use ggez::mint::{Point2, Vector2};
 use log::error;
 use pub_sub::{PubSub, Subscription};
 use tracing::{debug_span, instrument};
-use messages::{Interface, LogMessageType, RenderSettingsData, VrMessage};
+use messages::{DisplayGeometry, Interface, LogMessageType, RenderSettingsData, VrMessage};
 use crate::image_loader::{dynamic_to_ggez, ImageLoader};
+use crate::hud::Hud;
+use crate::interface::{Line, OpenInterface, Toasts};
 use crate::image_post_processing::postprocess;
-use messages::file_config::{read_config, save_config};
-use crate::transform::{left_offset_left, right_offset_right, TransformSet};
+use crate::inference::HeadOrientation;
+use crate::lens::LensPass;
+use crate::models::registry::ModelRegistry;
+use crate::reprojection::CameraTracker;
+use crate::spectator::Spectator;
+use messages::file_config::{read_config, update_config};
+use crate::transform::{left_offset_left, right_offset_right, Display, TransformSet};
 
 pub struct Images {
     pub left_back: Image,
@@ -33,17 +49,29 @@ pub struct Images {
 struct MainWindowState {
     loader: ImageLoader,
     lowest_level: Option<Images>,
+    /// Both eyes are composited offscreen first so the lens pass distorts each eye as a whole
+    eye_targets: (Image, Image),
+    lens: LensPass,
+    spectator: Option<Spectator>,
     settings: RenderSettingsData,
     msgbus: PubSub<VrMessage>,
     subscription: Subscription<VrMessage>,
     tick: u64,
+    orientation: HeadOrientation,
+    camera: CameraTracker,
+    hud: Hud,
     fps_buffer: [u128; 20],
     last_frame: Instant,
     fps_buf_idx: usize,
-    interface: Option<Interface>,
+    interface: Option<OpenInterface>,
+    toasts: Toasts,
     whl_rot: i128,
     whl_btn: bool,
     last_whl_btn: bool,
+    /// Waits for the other subsystems, runs once when the window closes
+    on_quit: Option<Box<dyn FnOnce()>>,
+    /// `VrMessage::Shutdown` was already sent by someone else
+    shutting_down: bool,
 }
 
 
@@ -56,52 +84,114 @@ impl Debug for MainWindowState {
 }
 
 impl MainWindowState {
-    pub fn new(ctx: &mut Context, pub_sub: PubSub<VrMessage>) -> Self {
-        let config = read_config();
+    pub fn new(ctx: &mut Context, config: RenderSettingsData, pub_sub: PubSub<VrMessage>) -> GameResult<Self> {
         ctx.gfx.add_font(
             "Arial",
             graphics::FontData::from_slice(include_bytes!("../../../Arial.ttf")).unwrap(),
         );
 
-        MainWindowState {
-            loader: ImageLoader::new(),
+        let display = Display::new(&config.display, ctx.gfx.drawable_size());
+        let eye_targets = eye_targets(ctx, &display);
+        let lens = LensPass::new(ctx, &display, &config.left_eye, &config.right_eye)?;
+        let camera = CameraTracker::new(&config.reprojection);
+        let hud = Hud::new(&config.hud);
+
+        Ok(MainWindowState {
+            loader: ImageLoader::new(&config, pub_sub.clone()),
             lowest_level: None,
+            eye_targets,
+            lens,
+            spectator: Spectator::new(&config.spectator, pub_sub.clone()),
             settings: config,
             subscription: pub_sub.subscribe(),
             msgbus: pub_sub,
             tick: 0,
+            orientation: HeadOrientation::default(),
+            camera,
+            hud,
             fps_buffer: [0; 20],
             last_frame: Instant::now(),
             fps_buf_idx: 0,
             interface: None,
+            toasts: Toasts::default(),
             whl_rot: 0,
             whl_btn: false,
             last_whl_btn: false,
+            on_quit: None,
+            shutting_down: false,
+        })
+    }
+
+    /// Saves `change` to the config file, `self.settings` is left alone
+    fn save(&self, change: impl FnOnce(&mut RenderSettingsData)) {
+        if let Err(e) = update_config(change) {
+            let _ = self.msgbus.send(VrMessage::Log {
+                message: format!("Failed to save the config: {}", e),
+                message_type: LogMessageType::Error,
+            });
         }
     }
 
     #[instrument]
-    fn process_bus(&mut self) {
+    fn process_bus(&mut self, ctx: &mut Context) {
         while let Ok(message) = self.subscription.try_recv() {
+            self.hud.process(&message);
             match message {
                 VrMessage::VrDistanceConfiguration { distance_between_b, distance_between_f, v_offset, distance_between_u } => {
-                    self.settings.space_between_back = distance_between_b;
-                    self.settings.space_between_front = distance_between_f;
-                    self.settings.space_between_ui = distance_between_u;
-                    self.settings.v_offset = v_offset;
-                    save_config(&self.settings);
+                    self.settings.space_between_back_mm = distance_between_b;
+                    self.settings.space_between_front_mm = distance_between_f;
+                    self.settings.space_between_ui_mm = distance_between_u;
+                    self.settings.v_offset_mm = v_offset;
+
+                    // the rest of the config, like the PIN, may have changed since it was read
+                    self.save(|file_config| {
+                        file_config.space_between_back_mm = distance_between_b;
+                        file_config.space_between_front_mm = distance_between_f;
+                        file_config.space_between_ui_mm = distance_between_u;
+                        file_config.v_offset_mm = v_offset;
+                    });
                 }
 
-                VrMessage::ModelConfiguration { model, config } => {
-                    self.settings.model = model;
-                    self.settings.model_configuration = config;
-                    save_config(&self.settings);
+                VrMessage::LensConfiguration { left, right } => {
+                    self.save(|file_config| {
+                        file_config.left_eye.lens = left.clone();
+                        file_config.right_eye.lens = right.clone();
+                    });
 
-                    self.loader.reload(&self.settings);
+                    self.settings.left_eye.lens = left;
+                    self.settings.right_eye.lens = right;
+                }
+
+                VrMessage::ModelConfiguration { model, config } => {
                     let _ = self.msgbus.send(VrMessage::Log {
-                        message: format!("Model changed to {:?}", model),
+                        message: format!("Switching model to {}", model),
                         message_type: LogMessageType::Info,
                     });
+
+                    // saved by the inference worker once the model is loaded, a broken one is never persisted
+                    self.settings.model_name = model;
+                    self.settings.model_configuration = config;
+                    self.loader.reload(&self.settings);
+                }
+
+                VrMessage::CameraServoPosition { yaw, pitch } => {
+                    self.camera.command(HeadOrientation { yaw, pitch });
+                }
+
+                VrMessage::SetReprojection { config } => {
+                    self.camera.configure(&config);
+                    self.save(|file_config| file_config.reprojection = config.clone());
+                    self.settings.reprojection = config;
+                }
+
+                VrMessage::SetMaskSmoothing { config } => {
+                    self.loader.set_smoothing(&config);
+                    self.save(|file_config| file_config.mask_smoothing = config.clone());
+                    self.settings.mask_smoothing = config;
+                }
+
+                VrMessage::GyroscopeReading { yaw, pitch, .. } => {
+                    self.orientation = HeadOrientation { yaw, pitch };
                 }
 
                 VrMessage::WheelState { rotation, left_button, right_button, .. } => {
@@ -109,8 +199,21 @@ impl MainWindowState {
                     self.whl_btn = left_button || right_button;
                 }
 
+                VrMessage::ShowRenderedInterface { interface: Interface::Toast { text, duration_ms } } => {
+                    self.toasts.push(text, duration_ms);
+                }
+
                 VrMessage::ShowRenderedInterface { interface } => {
-                    self.interface = Some(interface)
+                    self.interface = Some(OpenInterface::new(interface, self.whl_rot))
+                }
+
+                VrMessage::HideRenderedInterface {} | VrMessage::CancelInterface {} => {
+                    self.interface = None;
+                }
+
+                VrMessage::Shutdown {} => {
+                    self.shutting_down = true;
+                    ctx.request_quit();
                 }
 
                 _ => {}
@@ -136,27 +239,28 @@ impl MainWindowState {
         tracy_client::frame_mark();
     }
 
-    fn draw_input_number(&mut self, ctx: &mut Context, canvas: &mut graphics::Canvas, text: String, pos: Vec2) -> GameResult {
-        let chosen_number = ((self.whl_rot / 20) % 10).abs();
-        canvas.draw(
-            graphics::Text::new(format!("{}{}\n<-> Rad | [x] Taster", text, chosen_number))
-                .set_font("Arial")
-                .set_scale(12.)
-            , pos);
-
-        Ok(())
+    fn draw_lines(canvas: &mut graphics::Canvas, lines: &[Line], pos: Vec2) {
+        for (row, line) in lines.iter().enumerate() {
+            let color = if line.selected { Color::YELLOW } else { Color::WHITE };
+            canvas.draw(
+                graphics::Text::new(&line.text)
+                    .set_font("Arial")
+                    .set_scale(12.),
+                DrawParam::default().dest(pos + Vec2::new(0.0, row as f32 * 14.0)).color(color),
+            );
+        }
     }
 }
 
 impl EventHandler for MainWindowState {
     #[instrument]
     fn update(&mut self, ctx: &mut Context) -> GameResult {
-        self.tick += 1;
-        self.tick %= 3;
-        self.process_bus();
+        self.tick = self.tick.wrapping_add(1);
+        self.process_bus(ctx);
+        self.camera.update();
 
         let ((lf, lb), (rf, rb)) = debug_span!("loader.images()")
-            .in_scope(|| self.loader.images(self.tick == 0));
+            .in_scope(|| self.loader.images(self.orientation));
 
         let (lb, lf, rb, rf) = debug_span!("postprocess")
             .in_scope(|| (
@@ -176,20 +280,18 @@ impl EventHandler for MainWindowState {
                 });
             });
 
-        if self.whl_btn && !self.last_whl_btn {
-            let number = ((self.whl_rot / 20) % 10).abs();
-            if let Some(interface) = &self.interface {
-                match interface {
-                    Interface::InputNumberAndConfirm { .. } => {
-                        let _ = self.msgbus.send(VrMessage::InterfaceConfirm {
-                            data: number as i32,
-                        });
-
-                        self.interface = None;
-                    }
-                }
+        if let Some(interface) = &mut self.interface {
+            let pressed = self.whl_btn && !self.last_whl_btn;
+            let response = match pressed {
+                true => interface.press(self.whl_rot),
+                false => None,
+            }.or_else(|| interface.tick());
+
+            if let Some(data) = response {
+                let _ = self.msgbus.send(VrMessage::InterfaceConfirm { data });
+                self.interface = None;
             }
-        };
+        }
 
         self.last_whl_btn = self.whl_btn;
 
@@ -197,71 +299,109 @@ impl EventHandler for MainWindowState {
     }
 
     fn draw(&mut self, ctx: &mut Context) -> GameResult {
-        let transformations = TransformSet::from(&self.settings);
+        // recomputed every frame, the window does not have to match the configured resolution
+        let display = Display::new(&self.settings.display, ctx.gfx.drawable_size());
+        let viewport = display.eye_viewport();
+        if self.eye_targets.0.width() != viewport.0 as u32 || self.eye_targets.0.height() != viewport.1 as u32 {
+            self.eye_targets = eye_targets(ctx, &display);
+        }
 
-        let mut canvas = graphics::Canvas::from_frame(ctx, Color::BLACK);
+        let transformations = TransformSet::from(&self.settings, &display);
+        let right_origin = display.right_eye_origin();
+
+        let mut left = Canvas::from_image(ctx, self.eye_targets.0.clone(), Color::BLACK);
+        let mut right = Canvas::from_image(ctx, self.eye_targets.1.clone(), Color::BLACK);
         if let Some(lowest_level) = &self.lowest_level {
-            canvas.draw(&lowest_level.left_back, DrawParam {
-                transform: Transform::Values {
-                    dest: Point2::from(transformations.position_left_back),
-                    rotation: 0.0,
-                    scale: Vector2 { x: 1.0, y: 1.0 },
-                    offset: Point2 { x: 0.0, y: 0.0 },
-                },
-                ..Default::default()
-            });
-            canvas.draw(&lowest_level.left_front, DrawParam {
-                transform: Transform::Values {
-                    dest: Point2::from(transformations.position_left_front),
-                    rotation: 0.0,
-                    scale: Vector2 { x: 1.0, y: 1.0 },
-                    offset: Point2 { x: 0.0, y: 0.0 },
-                },
-                ..Default::default()
-            });
-            canvas.draw(&lowest_level.right_back, DrawParam {
-                transform: Transform::Values {
-                    dest: Point2::from(transformations.position_right_back),
-                    rotation: 0.0,
-                    scale: Vector2 { x: 1.0, y: 1.0 },
-                    offset: Point2 { x: 0.0, y: 0.0 },
-                },
-                ..Default::default()
-            });
-            canvas.draw(&lowest_level.right_front, DrawParam {
-                transform: Transform::Values {
-                    dest: Point2::from(transformations.position_right_front),
-                    rotation: 0.0,
-                    scale: Vector2 { x: 1.0, y: 1.0 },
-                    offset: Point2 { x: 0.0, y: 0.0 },
-                },
-                ..Default::default()
-            });
+            let (size_left, size_right) = (transformations.size_left, transformations.size_right);
+            // only the car cameras lag behind the head, the hand cameras are mounted on it
+            let warp_left = self.camera.offset(self.orientation, display.pixels_per_radian(&self.settings.left_eye));
+            let warp_right = self.camera.offset(self.orientation, display.pixels_per_radian(&self.settings.right_eye));
+            draw_image(&mut left, &lowest_level.left_back, transformations.position_left_back + warp_left, size_left);
+            draw_image(&mut left, &lowest_level.left_front, transformations.position_left_front, size_left);
+            draw_image(&mut right, &lowest_level.right_back, transformations.position_right_back - right_origin + warp_right, size_right);
+            draw_image(&mut right, &lowest_level.right_front, transformations.position_right_front - right_origin, size_right);
         }
 
-        if let Some(interface) = &self.interface {
-            match interface {
-                Interface::InputNumberAndConfirm { text } => {
-                    let left = left_offset_left(&(self.settings.space_between_ui as f32));
-                    let right = right_offset_right(&(self.settings.space_between_ui as f32));
-                    let text = text.clone();
+        let space_between_ui = self.settings.space_between_ui_mm;
+        self.hud.draw(ctx, &mut left, &display, true, space_between_ui)?;
+        self.hud.draw(ctx, &mut right, &display, false, space_between_ui)?;
 
-                    self.draw_input_number(ctx, &mut canvas, text.clone(), left)?;
-                    self.draw_input_number(ctx, &mut canvas, text.clone(), right)?;
-                }
+        let mut lines = match &self.interface {
+            Some(interface) => interface.lines(self.whl_rot),
+            None => vec![],
+        };
+        lines.extend(self.toasts.lines());
+        if !lines.is_empty() {
+            let left_position = left_offset_left(&display, &space_between_ui);
+            let right_position = right_offset_right(&display, &space_between_ui) - right_origin;
+
+            Self::draw_lines(&mut left, &lines, left_position);
+            Self::draw_lines(&mut right, &lines, right_position);
+        }
+
+        left.finish(ctx)?;
+        right.finish(ctx)?;
+
+        if let Some(spectator) = &mut self.spectator {
+            if let Err(e) = spectator.capture(ctx, &self.eye_targets.0, &self.eye_targets.1) {
+                error!("Failed to capture spectator frame: {}", e);
             }
         }
 
+        self.lens.update(ctx, &display, &self.settings.left_eye, &self.settings.right_eye);
+        let mut canvas = Canvas::from_frame(ctx, Color::BLACK);
+        self.lens.draw(&mut canvas, &self.eye_targets.0, Vec2::ZERO, &self.eye_targets.1, right_origin);
+
         self.finish_frame();
         canvas.finish(ctx)
     }
+
+    /// Closing the window shuts everything else down as well, the window stays until they are done
+    fn quit_event(&mut self, _ctx: &mut Context) -> GameResult<bool> {
+        if !self.shutting_down {
+            self.shutting_down = true;
+            let _ = self.msgbus.send(VrMessage::Shutdown {});
+        }
+        if let Some(on_quit) = self.on_quit.take() {
+            on_quit();
+        }
+        Ok(false)
+    }
+}
+
+fn eye_targets(ctx: &mut Context, display: &Display) -> (Image, Image) {
+    let (width, height) = display.eye_viewport();
+    let eye_target = |ctx: &mut Context| Image::new_canvas_image(
+        ctx,
+        ctx.gfx.surface_format(),
+        width as u32,
+        height as u32,
+        1,
+    );
+    (eye_target(ctx), eye_target(ctx))
+}
+
+/// Draws `image` stretched to `size` pixels
+fn draw_image(canvas: &mut Canvas, image: &Image, dest: Vec2, size: Vec2) {
+    canvas.draw(image, DrawParam {
+        transform: Transform::Values {
+            dest: Point2::from(dest),
+            rotation: 0.0,
+            scale: Vector2 {
+                x: size.x / image.width().max(1) as f32,
+                y: size.y / image.height().max(1) as f32,
+            },
+            offset: Point2 { x: 0.0, y: 0.0 },
+        },
+        ..Default::default()
+    });
 }
 
-fn build_context(fullscreen_type: FullscreenType) -> GameResult<(Context, EventLoop<()>)> {
+fn build_context(display: &DisplayGeometry, fullscreen_type: FullscreenType) -> GameResult<(Context, EventLoop<()>)> {
     let ctx = ggez::ContextBuilder::new("vr_renderer", "bloeckchen")
         .window_mode(
             WindowMode::default()
-                .dimensions(800.0, 480.0)
+                .dimensions(display.resolution.0 as f32, display.resolution.1 as f32)
                 .borderless(true)
                 .fullscreen_type(fullscreen_type)
                 .resizable(false)
@@ -275,28 +415,47 @@ fn build_context(fullscreen_type: FullscreenType) -> GameResult<(Context, EventL
     Ok(ctx)
 }
 
-#[cfg(not(feature = "fullscreen"))]
-fn build_context_according_to_config() -> GameResult<(Context, EventLoop<()>)> {
-    build_context(FullscreenType::Windowed)
-}
+/// Whether `vr_render_main` opens a fullscreen window when nobody says otherwise
+pub const FULLSCREEN_BY_DEFAULT: bool = cfg!(feature = "fullscreen");
 
-#[cfg(feature = "fullscreen")]
-fn build_context_according_to_config() -> GameResult<(Context, EventLoop<()>)> {
-    build_context(FullscreenType::True)
-}
+/// Runs until the window is closed or `VrMessage::Shutdown` arrives, `on_quit` is called before the process exits.
+/// Without a window (no display, no GPU) it carries on headless, if the renderer fails after that it shuts the rest down.
+pub fn vr_render_main(pub_sub: PubSub<VrMessage>, fullscreen: bool, on_quit: impl FnOnce() + 'static) {
+    ModelRegistry::spawn(pub_sub.clone());
 
-pub fn vr_render_main(pub_sub: PubSub<VrMessage>) {
-    let result = build_context_according_to_config();
+    let config = read_config();
+    let fullscreen_type = if fullscreen { FullscreenType::True } else { FullscreenType::Windowed };
+    let result = build_context(&config.display, fullscreen_type);
 
     let (mut ctx, event_loop) = match result {
         Ok((ctx, event_loop)) => (ctx, event_loop),
-        Err(_) => {
-            error!("Failed to build context");
+        Err(e) => {
+            error!("Failed to build context, continuing headless: {}", e);
+            let _ = pub_sub.send(VrMessage::Log {
+                message: "No window could be opened, running headless".to_string(),
+                message_type: LogMessageType::Warning,
+            });
+            headless::run(pub_sub, config, on_quit);
             return;
         }
     };
 
-    let state = MainWindowState::new(&mut ctx, pub_sub);
+    let mut state = match MainWindowState::new(&mut ctx, config, pub_sub.clone()) {
+        Ok(state) => state,
+        Err(e) => {
+            error!("Failed to set up the renderer: {}", e);
+            let _ = pub_sub.send(VrMessage::Shutdown {});
+            on_quit();
+            return;
+        }
+    };
+    state.on_quit = Some(Box::new(on_quit));
 
     ggez::event::run(ctx, event_loop, state);
 }
+
+/// Everything but the window, see `headless.segmentation` for what still runs. Returns after `VrMessage::Shutdown`.
+pub fn vr_headless_main(pub_sub: PubSub<VrMessage>, on_quit: impl FnOnce()) {
+    ModelRegistry::spawn(pub_sub.clone());
+    headless::run(pub_sub, read_config(), on_quit);
+}
@@ -0,0 +1,155 @@
+use std::time::Instant;
+use image::{DynamicImage, GrayImage, Luma, Rgb};
+use imageproc::distance_transform::Norm;
+use tracing::debug_span;
+use messages::{ColorKeyConfig, ColorSpace};
+use crate::models::{Instance, SegmentationModel, StageTimings};
+use crate::models::yolo::Bbox;
+
+/// Classical skin color / chroma key masking, a few milliseconds per frame on the CPU
+pub struct ColorKeySegmentationModel {
+    config: ColorKeyConfig,
+    input_size: (u32, u32),
+    timings: StageTimings,
+}
+
+impl ColorKeySegmentationModel {
+    pub fn new(config: &ColorKeyConfig) -> Self {
+        Self {
+            config: config.clone(),
+            input_size: (config.width, 0),
+            timings: StageTimings::default(),
+        }
+    }
+
+    fn channels(&self, pixel: &Rgb<u8>) -> [f32; 3] {
+        let [r, g, b] = pixel.0.map(|it| it as f32);
+        match self.config.color_space {
+            ColorSpace::YCrCb => {
+                let y = 0.299 * r + 0.587 * g + 0.114 * b;
+                [y, (r - y) * 0.713 + 128.0, (b - y) * 0.564 + 128.0]
+            }
+            ColorSpace::Hsv => {
+                let max = r.max(g).max(b);
+                let delta = max - r.min(g).min(b);
+                let hue = if delta == 0.0 {
+                    0.0
+                } else if max == r {
+                    60.0 * ((g - b) / delta).rem_euclid(6.0)
+                } else if max == g {
+                    60.0 * ((b - r) / delta + 2.0)
+                } else {
+                    60.0 * ((r - g) / delta + 4.0)
+                };
+                let saturation = if max == 0.0 { 0.0 } else { delta / max };
+                [hue, saturation, max / 255.0]
+            }
+        }
+    }
+
+    fn matches(&self, pixel: &Rgb<u8>) -> bool {
+        let wraps = self.config.color_space == ColorSpace::Hsv;
+        let inside = self.channels(pixel)
+            .iter()
+            .zip(self.config.ranges.iter())
+            .enumerate()
+            .all(|(channel, (value, (min, max)))| {
+                if channel == 0 && wraps && min > max {
+                    value >= min || value <= max
+                } else {
+                    value >= min && value <= max
+                }
+            });
+        inside != self.config.invert
+    }
+
+    fn key(&self, image: &DynamicImage) -> Option<Instance> {
+        let _span = debug_span!("color_key").entered();
+        let width = self.config.width.clamp(1, image.width().max(1));
+        let height = (image.height() as u64 * width as u64 / image.width().max(1) as u64).max(1) as u32;
+        let small = image.resize_exact(width, height, image::imageops::FilterType::Nearest).to_rgb8();
+
+        let mut mask = GrayImage::from_fn(width, height, |x, y| {
+            Luma([if self.matches(small.get_pixel(x, y)) { 255 } else { 0 }])
+        });
+        if self.config.cleanup > 0 {
+            mask = imageproc::morphology::open(&mask, Norm::LInf, self.config.cleanup);
+            mask = imageproc::morphology::close(&mask, Norm::LInf, self.config.cleanup);
+        }
+
+        let (mut x0, mut y0, mut x1, mut y1) = (width, height, 0, 0);
+        for (x, y, pixel) in mask.enumerate_pixels() {
+            if pixel.0[0] != 0 {
+                (x0, y0, x1, y1) = (x0.min(x), y0.min(y), x1.max(x + 1), y1.max(y + 1));
+            }
+        }
+        if x1 == 0 {
+            return None;
+        }
+
+        let scale = image.width() as f32 / width as f32;
+        let bbox = Bbox::new(
+            x0 as f32 * scale,
+            y0 as f32 * scale,
+            (x1 - x0) as f32 * scale,
+            (y1 - y0) as f32 * scale,
+            0,
+            1.0,
+        );
+        // scaling up bilinearly gives the mask a soft edge for free
+        let mask = DynamicImage::ImageLuma8(mask)
+            .resize_exact(image.width(), image.height(), image::imageops::FilterType::Triangle)
+            .to_luma8();
+
+        Some(Instance {
+            name: "color key".to_string(),
+            confidence: 1.0,
+            bbox,
+            mask,
+        })
+    }
+}
+
+impl SegmentationModel for ColorKeySegmentationModel {
+    fn predict(&mut self, images: &Vec<DynamicImage>) -> Vec<Option<Vec<Instance>>> {
+        let _span = debug_span!("ColorKeySegmentationModel::predict").entered();
+        if let Some(image) = images.first() {
+            let width = self.config.width.clamp(1, image.width().max(1));
+            self.input_size = (width, (image.height() as u64 * width as u64 / image.width().max(1) as u64) as u32);
+        }
+
+        let started = Instant::now();
+        let instances = images
+            .iter()
+            .map(|image| Some(self.key(image).into_iter().collect()))
+            .collect();
+        // keying is all there is, there are no separate pre- and postprocessing steps
+        self.timings.inference = started.elapsed();
+        instances
+    }
+
+    fn warm_up(&mut self) -> anyhow::Result<()> {
+        self.predict(&vec![DynamicImage::new_rgb8(640, 480)]);
+        Ok(())
+    }
+
+    fn name(&self) -> String {
+        "color key".to_string()
+    }
+
+    fn timings(&self) -> StageTimings {
+        self.timings
+    }
+
+    fn is_gpu(&self) -> bool {
+        false
+    }
+
+    fn execution_provider(&self) -> String {
+        "ColorKey".to_string()
+    }
+
+    fn input_size(&self) -> (u32, u32) {
+        self.input_size
+    }
+}
@@ -1,30 +1,74 @@
-use crate::models::yolonnx::YoloV8ONNXModelType::*;
-use crate::models::yolonnx::YoloV8ONNXQuantization::*;
+use std::time::Duration;
+use log::warn;
+use crate::models::colorkey::ColorKeySegmentationModel;
+use crate::models::registry::{ModelRegistry, MODEL_DIRECTORY};
+use crate::models::yolo::Bbox;
 use crate::models::yolonnx::YoloONNXSegmentationModel;
 use image::{DynamicImage, GrayImage};
-use messages::{ModelType, RenderSettingsData};
+use messages::{RenderSettingsData, Segmenter};
 
 pub mod yolo;
+pub mod registry;
 mod yolonnx;
+mod colorkey;
 
 
-pub trait SegmentationModel {
-    fn predict(&mut self, images: &Vec<DynamicImage>) -> Vec<Option<GrayImage>>;
+#[derive(Clone, Copy, Debug, Default)]
+pub struct StageTimings {
+    pub preprocess: Duration,
+    pub inference: Duration,
+    pub postprocess: Duration,
+}
+
+/// One detected object, the mask holds the model's confidence per pixel and has the size of
+/// the image it was found in
+#[derive(Clone)]
+pub struct Instance {
+    pub name: String,
+    pub confidence: f32,
+    pub bbox: Bbox,
+    pub mask: GrayImage,
+}
+
+/// Models are built on a loader thread and handed to the inference thread, hence `Send`
+pub trait SegmentationModel: Send {
+    /// `None` if inference failed for an image, an empty list if nothing was found in it
+    fn predict(&mut self, images: &Vec<DynamicImage>) -> Vec<Option<Vec<Instance>>>;
+    /// Runs one full batch and fails instead of swallowing errors like `predict` does
+    fn warm_up(&mut self) -> anyhow::Result<()>;
+    /// What the wizard is told is running, the file name for ONNX models
+    fn name(&self) -> String;
+    /// Stage durations of the last `predict`
+    fn timings(&self) -> StageTimings {
+        StageTimings::default()
+    }
     fn is_gpu(&self) -> bool;
+    fn execution_provider(&self) -> String;
+    fn input_size(&self) -> (u32, u32);
+}
+
+/// `Segmenter::Auto` uses the color key when YOLO would run on the CPU. `no_model_loaded` also lets it fall back
+/// when YOLO fails to load, with a model to keep the error is returned like for `Segmenter::Yolo`
+pub fn load_model(config: &RenderSettingsData, no_model_loaded: bool) -> anyhow::Result<Box<dyn SegmentationModel>> {
+    let color_key = || Box::new(ColorKeySegmentationModel::new(&config.model_configuration.color_key));
+    match config.model_configuration.segmenter {
+        Segmenter::Yolo => load_yolo(config),
+        Segmenter::ColorKey => Ok(color_key()),
+        Segmenter::Auto => match load_yolo(config) {
+            Ok(model) if !model.is_gpu() => {
+                warn!("{} would run on the CPU, using the color key instead", config.model_name);
+                Ok(color_key())
+            }
+            Err(e) if no_model_loaded => {
+                warn!("Failed to load {}, using the color key instead: {:#}", config.model_name, e);
+                Ok(color_key())
+            }
+            loaded => loaded,
+        },
+    }
 }
 
-pub fn load_model(config: &RenderSettingsData) -> Box<dyn SegmentationModel> {
-    Box::new(
-        match config.model {
-            ModelType::YoloV8mInt8ONNX => { YoloONNXSegmentationModel::new(V8m(Int8), &config.model_configuration) }
-            ModelType::YoloV8mHalfONNX => { YoloONNXSegmentationModel::new(V8m(Half), &config.model_configuration) }
-            ModelType::YoloV8mFullONNX => { YoloONNXSegmentationModel::new(V8m(None), &config.model_configuration) }
-            ModelType::YoloV11sInt8ONNX => { YoloONNXSegmentationModel::new(V11s(Int8), &config.model_configuration) }
-            ModelType::YoloV11sHalfONNX => { YoloONNXSegmentationModel::new(V11s(Half), &config.model_configuration) }
-            ModelType::YoloV11sFullONNX => { YoloONNXSegmentationModel::new(V11s(None), &config.model_configuration) }
-            ModelType::YoloV11mInt8ONNX => { YoloONNXSegmentationModel::new(V11m(Int8), &config.model_configuration) }
-            ModelType::YoloV11mHalfONNX => { YoloONNXSegmentationModel::new(V11m(Half), &config.model_configuration) }
-            ModelType::YoloV11mFullONNX => { YoloONNXSegmentationModel::new(V11m(None), &config.model_configuration) }
-        }
-    )
+fn load_yolo(config: &RenderSettingsData) -> anyhow::Result<Box<dyn SegmentationModel>> {
+    let path = ModelRegistry::verify(MODEL_DIRECTORY, &config.model_name)?;
+    Ok(Box::new(YoloONNXSegmentationModel::new(&path, &config.model_configuration)?))
 }
@@ -0,0 +1,201 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::SystemTime;
+use anyhow::{bail, Context, Result};
+use log::{info, warn};
+use pub_sub::PubSub;
+use sha2::{Digest, Sha256};
+use messages::{ModelInfo, VrMessage};
+use crate::models::yolo::OrtBackend;
+
+pub const MODEL_DIRECTORY: &str = "./onnx";
+
+/// `sha256sum *.onnx > SHA256SUMS` in the model directory, models that are not listed are not verified
+const CHECKSUM_FILE: &str = "SHA256SUMS";
+
+struct ScannedModel {
+    modified: Option<SystemTime>,
+    len: u64,
+    info: ModelInfo,
+}
+
+pub struct ModelRegistry {
+    directory: PathBuf,
+    models: HashMap<String, ScannedModel>,
+}
+
+impl ModelRegistry {
+    pub fn new(directory: impl AsRef<Path>) -> Self {
+        Self {
+            directory: directory.as_ref().to_path_buf(),
+            models: HashMap::new(),
+        }
+    }
+
+    /// Rescans the directory, models whose file did not change since the last scan are not inspected again
+    pub fn refresh(&mut self) {
+        let entries = match fs::read_dir(&self.directory) {
+            Ok(entries) => entries,
+            Err(e) => {
+                warn!("Failed to read model directory {}: {}", self.directory.display(), e);
+                self.models.clear();
+                return;
+            }
+        };
+
+        let checksums = read_checksums(&self.directory);
+        let mut models = HashMap::new();
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.extension().and_then(|it| it.to_str()) != Some("onnx") {
+                continue;
+            }
+
+            let Some(name) = path.file_name().and_then(|it| it.to_str()).map(str::to_string) else {
+                continue;
+            };
+            let (modified, len) = match entry.metadata() {
+                Ok(metadata) => (metadata.modified().ok(), metadata.len()),
+                Err(_) => (None, 0),
+            };
+
+            let scanned = match self.models.remove(&name) {
+                Some(scanned) if scanned.modified.is_some() && scanned.modified == modified && scanned.len == len => {
+                    ScannedModel {
+                        info: ModelInfo {
+                            verified: checksums.get(&name).map(|it| *it == scanned.info.checksum),
+                            ..scanned.info
+                        },
+                        ..scanned
+                    }
+                }
+                _ => ScannedModel {
+                    modified,
+                    len,
+                    info: inspect(&path, &name, checksums.get(&name)),
+                },
+            };
+            models.insert(name, scanned);
+        }
+
+        self.models = models;
+        info!("Found {} models in {}", self.models.len(), self.directory.display());
+    }
+
+    pub fn models(&self) -> Vec<ModelInfo> {
+        let mut models = self.models
+            .values()
+            .map(|it| it.info.clone())
+            .collect::<Vec<_>>();
+        models.sort_by(|a, b| a.name.cmp(&b.name));
+        models
+    }
+
+    /// Resolves a model name to its path, refusing anything outside the directory or with a mismatching checksum
+    pub fn verify(directory: impl AsRef<Path>, name: &str) -> Result<PathBuf> {
+        let directory = directory.as_ref();
+        if name.is_empty() || Path::new(name).file_name().and_then(|it| it.to_str()) != Some(name) {
+            bail!("Invalid model name {:?}", name);
+        }
+
+        let path = directory.join(name);
+        if !path.is_file() {
+            bail!("Model {} not found", path.display());
+        }
+
+        if let Some(expected) = read_checksums(directory).get(name) {
+            let actual = checksum(&path)?;
+            if *expected != actual {
+                bail!("Checksum mismatch for {}: expected {}, got {}", name, expected, actual);
+            }
+        }
+
+        Ok(path)
+    }
+
+    /// Scans once on startup and again whenever the wizard asks for the model list
+    pub fn spawn(bus: PubSub<VrMessage>) {
+        thread::Builder::new()
+            .name("model-registry".to_string())
+            .spawn(move || {
+                let subscription = bus.subscribe();
+                let mut registry = ModelRegistry::new(MODEL_DIRECTORY);
+                registry.refresh();
+                let _ = bus.send(VrMessage::ModelList { models: registry.models() });
+
+                while let Ok(message) = subscription.recv() {
+                    if let VrMessage::ListModels {} = message {
+                        registry.refresh();
+                        let _ = bus.send(VrMessage::ModelList { models: registry.models() });
+                    }
+                }
+            })
+            .expect("Failed to spawn model registry thread");
+    }
+}
+
+fn inspect(path: &Path, name: &str, expected: Option<&String>) -> ModelInfo {
+    let mut info = ModelInfo {
+        name: name.to_string(),
+        task: String::new(),
+        classes: vec![],
+        image_size: None,
+        dynamic_input: false,
+        checksum: String::new(),
+        verified: None,
+        error: None,
+    };
+
+    match checksum(path) {
+        Ok(checksum) => {
+            info.verified = expected.map(|it| *it == checksum);
+            info.checksum = checksum;
+        }
+        Err(e) => {
+            info.error = Some(format!("{:#}", e));
+            return info;
+        }
+    }
+
+    match OrtBackend::inspect(&path.to_string_lossy()) {
+        Ok(backend) => {
+            info.task = format!("{:?}", backend.task()).to_lowercase();
+            info.classes = backend.names().unwrap_or_default();
+            info.image_size = backend.image_size();
+            info.dynamic_input = backend.is_height_dynamic() && backend.is_width_dynamic();
+        }
+        Err(e) => {
+            warn!("Failed to inspect model {}: {:?}", name, e);
+            info.error = Some(format!("{:#}", e));
+        }
+    }
+
+    info
+}
+
+fn checksum(path: &Path) -> Result<String> {
+    let mut file = fs::File::open(path)
+        .with_context(|| format!("Failed to open {}", path.display()))?;
+    let mut hasher = Sha256::new();
+    std::io::copy(&mut file, &mut hasher)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+fn read_checksums(directory: &Path) -> HashMap<String, String> {
+    let Ok(content) = fs::read_to_string(directory.join(CHECKSUM_FILE)) else {
+        return HashMap::new();
+    };
+
+    content
+        .lines()
+        .filter_map(|line| {
+            let (hash, name) = line.split_once(char::is_whitespace)?;
+            // sha256sum marks binary mode with a leading '*'
+            let name = name.trim_start().trim_start_matches('*');
+            Some((name.to_string(), hash.to_lowercase()))
+        })
+        .collect()
+}
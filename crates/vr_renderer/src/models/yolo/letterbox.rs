@@ -0,0 +1,174 @@
+/// Where a frame ended up inside the model input. The frame is scaled to fit without distortion
+/// and centered, the remaining border is padding that boxes and masks must be mapped back out of.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Letterbox {
+    pub scale: f32,
+    pub pad_x: u32,
+    pub pad_y: u32,
+    /// Size of the scaled frame inside the model input
+    pub width: u32,
+    pub height: u32,
+    pub original_width: u32,
+    pub original_height: u32,
+}
+
+impl Letterbox {
+    pub fn new(original: (u32, u32), input: (u32, u32)) -> Self {
+        let (original_width, original_height) = (original.0.max(1), original.1.max(1));
+        let scale = (input.0 as f32 / original_width as f32).min(input.1 as f32 / original_height as f32);
+        let width = ((original_width as f32 * scale).round() as u32).clamp(1, input.0.max(1));
+        let height = ((original_height as f32 * scale).round() as u32).clamp(1, input.1.max(1));
+
+        Self {
+            scale,
+            pad_x: (input.0.saturating_sub(width)) / 2,
+            pad_y: (input.1.saturating_sub(height)) / 2,
+            width,
+            height,
+            original_width,
+            original_height,
+        }
+    }
+
+    /// Maps a point of the model input back onto the original frame
+    pub fn to_original(&self, x: f32, y: f32) -> (f32, f32) {
+        (
+            (x - self.pad_x as f32) / self.scale,
+            (y - self.pad_y as f32) / self.scale,
+        )
+    }
+
+    /// The part of a `grid_width` x `grid_height` map over the whole model input (like the mask
+    /// prototypes) that covers the frame, as `(x, y, width, height)` in grid cells
+    pub fn grid_region(&self, input: (u32, u32), grid_width: usize, grid_height: usize) -> (usize, usize, usize, usize) {
+        let x0 = (self.pad_x as usize * grid_width / input.0.max(1) as usize).min(grid_width - 1);
+        let y0 = (self.pad_y as usize * grid_height / input.1.max(1) as usize).min(grid_height - 1);
+        let x1 = ((self.pad_x + self.width) as usize * grid_width).div_ceil(input.0.max(1) as usize).clamp(x0 + 1, grid_width);
+        let y1 = ((self.pad_y + self.height) as usize * grid_height).div_ceil(input.1.max(1) as usize).clamp(y0 + 1, grid_height);
+        (x0, y0, x1 - x0, y1 - y0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use image::{imageops, Rgb, RgbImage};
+    use super::Letterbox;
+
+    fn assert_close(actual: (f32, f32), expected: (f32, f32), tolerance: f32) {
+        assert!(
+            (actual.0 - expected.0).abs() <= tolerance && (actual.1 - expected.1).abs() <= tolerance,
+            "{:?} is not within {} of {:?}", actual, tolerance, expected,
+        );
+    }
+
+    #[test]
+    fn landscape_frame_is_padded_vertically() {
+        let letterbox = Letterbox::new((640, 480), (640, 640));
+        assert_eq!(letterbox.scale, 1.0);
+        assert_eq!((letterbox.width, letterbox.height), (640, 480));
+        assert_eq!((letterbox.pad_x, letterbox.pad_y), (0, 80));
+    }
+
+    #[test]
+    fn portrait_frame_is_padded_horizontally() {
+        let letterbox = Letterbox::new((480, 640), (640, 640));
+        assert_eq!((letterbox.width, letterbox.height), (480, 640));
+        assert_eq!((letterbox.pad_x, letterbox.pad_y), (80, 0));
+    }
+
+    #[test]
+    fn odd_sizes_stay_inside_the_input() {
+        for (original, input) in [((333, 101), (320, 320)), ((101, 333), (320, 256)), ((7, 5), (3, 3)), ((1, 1), (640, 480))] {
+            let letterbox = Letterbox::new(original, input);
+            assert!(letterbox.pad_x + letterbox.width <= input.0, "{:?} into {:?}: {:?}", original, input, letterbox);
+            assert!(letterbox.pad_y + letterbox.height <= input.1, "{:?} into {:?}: {:?}", original, input, letterbox);
+            // the frame fills one axis, so the padding left on it is at most a rounding pixel
+            assert!(letterbox.width >= input.0 - 1 || letterbox.height >= input.1 - 1, "{:?} into {:?}: {:?}", original, input, letterbox);
+
+            // and is not distorted
+            let aspect = original.0 as f32 / original.1 as f32;
+            let scaled_aspect = letterbox.width as f32 / letterbox.height as f32;
+            let rounding = 1.0 / letterbox.width.min(letterbox.height) as f32;
+            assert!((aspect - scaled_aspect).abs() <= aspect * 2.0 * rounding, "{:?} into {:?}: {:?}", original, input, letterbox);
+        }
+    }
+
+    #[test]
+    fn odd_sized_frame_is_centered() {
+        let letterbox = Letterbox::new((333, 101), (320, 320));
+        assert_eq!((letterbox.width, letterbox.height), (320, 97));
+        assert_eq!((letterbox.pad_x, letterbox.pad_y), (0, 111));
+    }
+
+    #[test]
+    fn boxes_round_trip() {
+        for (original, input) in [((640, 480), (640, 640)), ((333, 101), (320, 320)), ((101, 333), (320, 256))] {
+            let letterbox = Letterbox::new(original, input);
+            let corners = [(0.0, 0.0), (original.0 as f32, original.1 as f32), (17.5, 42.25), (original.0 as f32 / 3.0, 1.0)];
+            for (x, y) in corners {
+                let into_input = (
+                    x * letterbox.scale + letterbox.pad_x as f32,
+                    y * letterbox.scale + letterbox.pad_y as f32,
+                );
+                assert_close(letterbox.to_original(into_input.0, into_input.1), (x, y), 1e-3);
+            }
+        }
+    }
+
+    #[test]
+    fn padding_maps_outside_the_frame() {
+        let letterbox = Letterbox::new((640, 480), (640, 640));
+        assert_close(letterbox.to_original(0.0, 0.0), (0.0, -80.0), 1e-3);
+        assert_close(letterbox.to_original(640.0, 640.0), (640.0, 560.0), 1e-3);
+    }
+
+    #[test]
+    fn grid_region_skips_the_padding() {
+        let letterbox = Letterbox::new((640, 480), (640, 640));
+        assert_eq!(letterbox.grid_region((640, 640), 160, 160), (0, 20, 160, 120));
+
+        let letterbox = Letterbox::new((480, 640), (640, 640));
+        assert_eq!(letterbox.grid_region((640, 640), 160, 160), (20, 0, 120, 160));
+    }
+
+    #[test]
+    fn grid_region_covers_odd_sized_frames() {
+        for (original, input, grid) in [((333, 101), (320, 320), (80, 80)), ((101, 333), (320, 256), (80, 64)), ((7, 5), (3, 3), (2, 2))] {
+            let letterbox = Letterbox::new(original, input);
+            let (x, y, width, height) = letterbox.grid_region(input, grid.0, grid.1);
+            let cell = (input.0 as f32 / grid.0 as f32, input.1 as f32 / grid.1 as f32);
+
+            assert!(width >= 1 && height >= 1 && x + width <= grid.0 && y + height <= grid.1);
+            // the region covers the whole frame and at most one cell of padding on each side
+            assert!(x as f32 * cell.0 <= letterbox.pad_x as f32);
+            assert!(y as f32 * cell.1 <= letterbox.pad_y as f32);
+            assert!((x + width) as f32 * cell.0 >= (letterbox.pad_x + letterbox.width) as f32);
+            assert!((y + height) as f32 * cell.1 >= (letterbox.pad_y + letterbox.height) as f32);
+            assert!((width as f32 - 2.0) * cell.0 <= letterbox.width as f32);
+            assert!((height as f32 - 2.0) * cell.1 <= letterbox.height as f32);
+        }
+    }
+
+    /// Letterboxes a synthetic frame like `batch_preprocess_mult` does and finds a marker again
+    #[test]
+    fn marker_in_synthetic_image_maps_back() {
+        let (marker_x, marker_y) = (40, 10);
+        let mut frame = RgbImage::new(64, 48);
+        for (x, y) in [(0, 0), (1, 0), (0, 1), (1, 1)] {
+            frame.put_pixel(marker_x + x, marker_y + y, Rgb([255, 255, 255]));
+        }
+
+        let letterbox = Letterbox::new(frame.dimensions(), (32, 32));
+        let scaled = imageops::resize(&frame, letterbox.width, letterbox.height, imageops::FilterType::Nearest);
+        let mut input = RgbImage::from_pixel(32, 32, Rgb([114, 114, 114]));
+        imageops::overlay(&mut input, &scaled, letterbox.pad_x as i64, letterbox.pad_y as i64);
+
+        let found = input
+            .enumerate_pixels()
+            .find(|(_, _, pixel)| pixel.0 == [255, 255, 255])
+            .map(|(x, y, _)| (x as f32, y as f32))
+            .expect("the marker survives scaling");
+
+        assert_close(letterbox.to_original(found.0, found.1), (marker_x as f32, marker_y as f32), 1.0 / letterbox.scale);
+    }
+}
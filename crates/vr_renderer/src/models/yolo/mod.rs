@@ -1,10 +1,12 @@
 pub mod model;
 mod yolo_result;
 mod ort_backend;
+mod letterbox;
 
 use std::io::{Read, Write};
 use ndarray::{Array, Ix1};
-use messages::ModelConfiguration;
+use messages::{InferenceDevice, ModelConfiguration};
+pub use crate::models::yolo::letterbox::Letterbox;
 pub use crate::models::yolo::model::YOLO;
 pub use crate::models::yolo::ort_backend::{Batch, OrtBackend, OrtConfig, OrtEP, YOLOTask};
 pub use crate::models::yolo::yolo_result::{Bbox, Embedding, Point2, YOLOResult};
@@ -24,9 +26,12 @@ pub struct Args {
     pub nm: Option<u32>,
     pub width: Option<u32>,
     pub height: Option<u32>,
+    pub cpu_size: Option<u32>,
+    pub threads: u16,
     pub conf: f32,
     pub iou: f32,
     pub kconf: f32,
+    pub classes: Vec<(String, f32)>,
     pub plot: bool,
     pub profile: bool,
 }
@@ -37,8 +42,8 @@ impl Args {
         Args {
             model: model_path,
             device_id: 0,
-            trt: false,
-            cuda: true,
+            trt: matches!(model_configuration.device, InferenceDevice::TensorRT),
+            cuda: !matches!(model_configuration.device, InferenceDevice::Cpu),
             batch: 2,
             batch_min: 1,
             batch_max: 32,
@@ -49,9 +54,15 @@ impl Args {
             nm: None,
             width: Some(640),
             height: Some(640),
+            cpu_size: Some(model_configuration.cpu_input_size),
+            threads: model_configuration.cpu_threads,
             conf: model_configuration.confidence,
             iou: model_configuration.iou,
             kconf: model_configuration.kconf,
+            classes: model_configuration.classes
+                .iter()
+                .map(|it| (it.name.clone(), it.confidence.unwrap_or(model_configuration.confidence)))
+                .collect(),
             plot: false,
             profile: false,
         }
@@ -1,12 +1,14 @@
 #![allow(clippy::type_complexity)]
 
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use image::{DynamicImage, GenericImageView, ImageBuffer, Luma};
 use ndarray::{s, Array, Axis, Ix1, IxDyn};
 use rand::{thread_rng, Rng};
 use std::path::PathBuf;
+use std::time::Instant;
 use tracing::{debug, span, trace, Level};
-use crate::models::yolo::{check_font, gen_time_string, non_max_suppression, Batch, Bbox, Embedding, OrtBackend, OrtConfig, OrtEP, Point2, YOLOResult, YOLOTask, SKELETON, Args, fastnms};
+use crate::models::StageTimings;
+use crate::models::yolo::{check_font, gen_time_string, non_max_suppression, Batch, Bbox, Embedding, OrtBackend, OrtConfig, OrtEP, Point2, YOLOResult, YOLOTask, SKELETON, Args, fastnms, Letterbox};
 
 pub struct YOLO {
     // YOLOv8 model for all yolo-tasks
@@ -22,9 +24,11 @@ pub struct YOLO {
     kconf: f32,
     iou: f32,
     names: Vec<String>,
+    class_conf: Vec<Option<f32>>,
     color_palette: Vec<(u8, u8, u8)>,
     profile: bool,
     plot: bool,
+    timings: StageTimings,
 }
 
 impl YOLO {
@@ -53,6 +57,8 @@ impl YOLO {
             task: config.task,
             trt_fp16: config.fp16,
             image_size: (config.height, config.width),
+            cpu_image_size: config.cpu_size,
+            threads: config.threads,
         };
         let engine = OrtBackend::build(ort_args)?;
 
@@ -63,20 +69,17 @@ impl YOLO {
             engine.width(),
             engine.task(),
         );
-        let nc = engine.nc().or(config.nc).unwrap_or_else(|| {
-            panic!("Failed to get num_classes, make it explicit with `--nc`");
-        });
+        let nc = engine.nc().or(config.nc)
+            .ok_or_else(|| anyhow!("Failed to get num_classes, make it explicit with `--nc`"))?;
         let (nk, nm) = match task {
             YOLOTask::Pose => {
-                let nk = engine.nk().or(config.nk).unwrap_or_else(|| {
-                    panic!("Failed to get num_keypoints, make it explicit with `--nk`");
-                });
+                let nk = engine.nk().or(config.nk)
+                    .ok_or_else(|| anyhow!("Failed to get num_keypoints, make it explicit with `--nk`"))?;
                 (nk, 0)
             }
             YOLOTask::Segment => {
-                let nm = engine.nm().or(config.nm).unwrap_or_else(|| {
-                    panic!("Failed to get num_masks, make it explicit with `--nm`");
-                });
+                let nm = engine.nm().or(config.nm)
+                    .ok_or_else(|| anyhow!("Failed to get num_masks, make it explicit with `--nm`"))?;
                 (0, nm)
             }
             _ => (0, 0),
@@ -85,6 +88,16 @@ impl YOLO {
         // class names
         let names = engine.names().unwrap_or(vec!["Unknown".to_string()]);
 
+        // confidence threshold per class, classes without one are dropped in postprocessing
+        let class_conf: Vec<Option<f32>> = if config.classes.is_empty() {
+            names.iter().map(|_| Some(config.conf)).collect()
+        } else {
+            names
+                .iter()
+                .map(|name| config.classes.iter().find(|(it, _)| it == name).map(|(_, conf)| *conf))
+                .collect()
+        };
+
         // color palette
         let mut rng = thread_rng();
         let color_palette: Vec<_> = names
@@ -101,6 +114,7 @@ impl YOLO {
         Ok(Self {
             engine,
             names,
+            class_conf,
             conf: config.conf,
             kconf: config.kconf,
             iou: config.iou,
@@ -114,6 +128,7 @@ impl YOLO {
             width,
             batch,
             task,
+            timings: StageTimings::default(),
         })
     }
 
@@ -142,7 +157,7 @@ impl YOLO {
                     let (w0, h0) = x.dimensions();
                     let w0 = w0 as f32;
                     let h0 = h0 as f32;
-                    let (_, w_new, h_new) = self.scale_wh(w0, h0, 640f32, 640f32); // f32 round
+                    let (_, w_new, h_new) = self.scale_wh(w0, h0, self.width() as f32, self.height() as f32); // f32 round
                     x.resize_exact(
                         w_new as u32,
                         h_new as u32,
@@ -172,7 +187,7 @@ impl YOLO {
         let mut ys = Array::ones((xs.len(), 3, self.height() as usize, self.width() as usize)).into_dyn();
         let x = &xs[0];
         let (w0, h0) = x.dimensions();
-        let (w_new, h_new) = self.scale_wh_int(w0 as f32, h0 as f32, 640, 640); // f32 round
+        let (w_new, h_new) = self.scale_wh_int(w0 as f32, h0 as f32, self.width(), self.height()); // f32 round
         let img = x.resize_exact(w_new, h_new, image::imageops::FilterType::Nearest);
 
         img.pixels().for_each(|(x, y, rgb)| {
@@ -198,7 +213,7 @@ impl YOLO {
 
         let x = &xs[0];
         let (w0, h0) = x.dimensions();
-        let (w_new, h_new) = self.scale_wh_int(w0 as f32, h0 as f32, 640, 640); // f32 round
+        let (w_new, h_new) = self.scale_wh_int(w0 as f32, h0 as f32, self.width(), self.height()); // f32 round
         let img = x.resize_exact(w_new, h_new, image::imageops::FilterType::Nearest);
         let rgb_img = img.to_rgb8();
         let (height, width) = (rgb_img.height() as usize, rgb_img.width() as usize);
@@ -210,78 +225,81 @@ impl YOLO {
         Ok(ys)
     }
 
-    pub fn batch_preprocess_mult(&mut self, xs: &Vec<DynamicImage>) -> Result<Array<f32, IxDyn>> {
+    /// Letterboxes every frame into the model input, the returned letterboxes map results back
+    pub fn batch_preprocess_mult(&mut self, xs: &Vec<DynamicImage>) -> Result<(Array<f32, IxDyn>, Vec<Letterbox>)> {
         let _span = span!(Level::TRACE, "batch_preprocess_mult").entered();
-        let mut ys = Array::ones((
+        // same gray padding ultralytics trains with
+        let mut ys = Array::from_elem((
             xs.len(),
             3,
             self.height() as usize,
             self.width() as usize,
-        )).into_dyn();
+        ), 114.0 / 255.0).into_dyn();
+        let mut letterboxes = Vec::with_capacity(xs.len());
 
         debug!("Preprocess");
 
         for (i, x) in xs.iter().enumerate() {
-            let (w0, h0) = x.dimensions();
-            let (w_new, h_new) = self.scale_wh_int(w0 as f32, h0 as f32, 640, 640); // f32 round
-            let img = x.resize_exact(w_new, h_new, image::imageops::FilterType::Nearest);
+            let letterbox = Letterbox::new(x.dimensions(), (self.width(), self.height()));
+            let img = x.resize_exact(letterbox.width, letterbox.height, image::imageops::FilterType::Nearest);
             let rgb_img = img.to_rgb8();
             let (height, width) = (rgb_img.height() as usize, rgb_img.width() as usize);
             let raw_data = rgb_img.into_raw(); // Length: height * width * 3
             let array = Array::from_shape_vec((height, width, 3), raw_data)?
                 .permuted_axes([2, 0, 1]) // Shape: (3, height, width)
                 .map(|&v| v as f32 / 255.0); // Normalize pixel values to [0, 1]
-            ys.slice_mut(s![i, .., 0..height, 0..width]).assign(&array);
+            let (x0, y0) = (letterbox.pad_x as usize, letterbox.pad_y as usize);
+            ys.slice_mut(s![i, .., y0..y0 + height, x0..x0 + width]).assign(&array);
+            letterboxes.push(letterbox);
         }
 
         debug!("Preprocess done");
 
-        Ok(ys)
+        Ok((ys, letterboxes))
     }
 
     pub fn run(&mut self, xs: &Vec<DynamicImage>) -> Result<Vec<YOLOResult>> {
         let _span = span!(Level::TRACE, "YOLO::run").entered();
         // pre-process
-        let xs_ = self.batch_preprocess_mult(xs)?;
+        let started = Instant::now();
+        let (xs_, letterboxes) = self.batch_preprocess_mult(xs)?;
+        self.timings.preprocess = started.elapsed();
         debug!("Preprocess done");
 
         // run
+        let started = Instant::now();
         let ys = self.engine.run(xs_, false)?;
+        self.timings.inference = started.elapsed();
         debug!("Run done");
 
         // post-process
-        let ys = self.fast_postprocess(ys)?;
+        let started = Instant::now();
+        let ys = self.fast_postprocess(ys, &letterboxes)?;
+        self.timings.postprocess = started.elapsed();
         debug!("Postprocess done");
         Ok(ys)
     }
 
+    /// Boxes and masks are in the geometry of the original frames, masks are soft (sigmoid * 255)
     pub fn fast_postprocess(
         &self,
         xs: Vec<Array<f32, IxDyn>>,
+        letterboxes: &[Letterbox],
     ) -> Result<Vec<YOLOResult>> {
         const CXYWH_OFFSET: usize = 4; // cxcywh
 
         let preds = &xs[0];
         let protos = xs.get(1);
 
-        let allowed_ids = self
-            .names()
-            .iter()
-            .enumerate()
-            .filter(|(_, it)| *it == "person" || *it == "clock")
-            .map(|(i, _)| i)
-            .collect::<Vec<usize>>();
-
         let mut ys = Vec::with_capacity(preds.len_of(Axis(0)));
         for (idx, anchor) in preds.axis_iter(Axis(0)).enumerate() {
 
             let _span = span!(Level::TRACE, "fast_postprocess", idx).entered();
 
-            // Input image dimensions
-            let width_original = 640f32;
-            let height_original = 640f32;
-            let ratio = (self.width() as f32 / width_original)
-                .min(self.height() as f32 / height_original);
+            let input = (self.width(), self.height());
+            let letterbox = letterboxes.get(idx).copied().unwrap_or_else(|| Letterbox::new(input, input));
+            let width_original = letterbox.original_width as f32;
+            let height_original = letterbox.original_height as f32;
 
             // Collect detections
             let mut detections: Vec<(Bbox, Option<Array<f32, Ix1>>)> = Vec::new();
@@ -301,18 +319,18 @@ impl YOLO {
                     .max_by(|a, b| a.1.partial_cmp(b.1).unwrap())
                     .unwrap();
 
-                // Confidence thresholding
-                if confidence < self.conf || !allowed_ids.contains(&id) {
-                    continue;
+                // Confidence thresholding, per mask class
+                match self.class_conf.get(id) {
+                    Some(Some(conf)) if confidence >= *conf => {}
+                    _ => continue,
                 }
 
                 debug!("id: {}, confidence: {}", id, confidence);
 
                 // Rescale bbox
-                let cx = bbox[0] / ratio;
-                let cy = bbox[1] / ratio;
-                let w = bbox[2] / ratio;
-                let h = bbox[3] / ratio;
+                let (cx, cy) = letterbox.to_original(bbox[0], bbox[1]);
+                let w = bbox[2] / letterbox.scale;
+                let h = bbox[3] / letterbox.scale;
                 let x = (cx - w / 2.0).clamp(0.0, width_original);
                 let y = (cy - h / 2.0).clamp(0.0, height_original);
                 let w = w.min(width_original - x);
@@ -350,24 +368,21 @@ impl YOLO {
                         // Apply activation function (e.g., sigmoid)
                         let mask = mask.mapv(|v| 1.0 / (1.0 + (-v).exp()));
 
-                        // Resize mask to original image size
-                        let mask_im = ImageBuffer::from_fn(nw as u32, nh as u32, |x, y| {
-                            Luma([(mask[(y as usize, x as usize)] * 255f32) as u8])
+                        // Only the part of the prototype covering the frame, padding would stretch the mask
+                        let (crop_x, crop_y, crop_width, crop_height) = letterbox.grid_region(input, nw, nh);
+                        let mask_im = ImageBuffer::from_fn(crop_width as u32, crop_height as u32, |x, y| {
+                            Luma([(mask[(crop_y + y as usize, crop_x + x as usize)] * 255f32) as u8])
                         });
 
-                        let mut mask_im = DynamicImage::ImageLuma8(mask_im)
+                        // Keep the probabilities, thresholding happens when the mask is composited
+                        let mask_im = DynamicImage::ImageLuma8(mask_im)
                             .resize_exact(
-                                width_original as u32,
-                                height_original as u32,
-                                image::imageops::FilterType::Nearest,
+                                letterbox.original_width,
+                                letterbox.original_height,
+                                image::imageops::FilterType::Triangle,
                             )
                             .to_luma8();
 
-                        // Binarize mask
-                        for pixel in mask_im.pixels_mut() {
-                            pixel.0[0] = if pixel.0[0] > 128 { 255 } else { 0 };
-                        }
-
                         y_masks.push(mask_im.into_raw());
                     }
                 }
@@ -676,6 +691,11 @@ impl YOLO {
         self.batch
     }
 
+    /// How long the stages of the last successful `run` took
+    pub fn timings(&self) -> StageTimings {
+        self.timings
+    }
+
     pub fn width(&self) -> u32 {
         self.width
     }
@@ -699,4 +719,8 @@ impl YOLO {
     pub fn names(&self) -> &Vec<String> {
         &self.names
     }
+
+    pub fn class_conf(&self) -> &Vec<Option<f32>> {
+        &self.class_conf
+    }
 }
\ No newline at end of file
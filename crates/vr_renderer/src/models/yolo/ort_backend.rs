@@ -1,11 +1,12 @@
-use anyhow::Result;
+use std::sync::Arc;
+use anyhow::{bail, Result};
 use half::f16;
 use ndarray::{Array, CowArray, IxDyn};
 use ort::execution_providers::{CUDAExecutionProviderOptions, TensorRTExecutionProviderOptions};
 use ort::tensor::TensorElementDataType;
 use ort::{Environment, ExecutionProvider, Session, SessionBuilder, Value};
 use regex::Regex;
-use tracing::{debug, trace};
+use tracing::{debug, trace, warn};
 
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
 pub enum YOLOTask {
@@ -82,6 +83,8 @@ pub struct OrtConfig {
     pub trt_fp16: bool,
     pub batch: Batch,
     pub image_size: (Option<u32>, Option<u32>),
+    pub cpu_image_size: Option<u32>,
+    pub threads: u16,
 }
 
 #[derive(Debug)]
@@ -106,17 +109,19 @@ impl OrtBackend {
 
         // get inputs
         let mut inputs = OrtInputs::new(&session);
+        Self::check_inputs(&inputs)?;
 
         // batch size
         let mut batch = args.batch;
         let batch = if inputs.shapes[0][0] == -1 {
             batch
         } else {
-            assert_eq!(
-                inputs.shapes[0][0] as u32, batch.opt,
-                "Expected batch size: {}, got {}. Try using `--batch {}`.",
-                inputs.shapes[0][0] as u32, batch.opt, inputs.shapes[0][0] as u32
-            );
+            if inputs.shapes[0][0] as u32 != batch.opt {
+                bail!(
+                    "Expected batch size: {}, got {}. Try using `--batch {}`.",
+                    inputs.shapes[0][0] as u32, batch.opt, inputs.shapes[0][0] as u32
+                );
+            }
             batch.opt = inputs.shapes[0][0] as u32;
             batch
         };
@@ -125,7 +130,7 @@ impl OrtBackend {
         let height = if inputs.shapes[0][2] == -1 {
             match args.image_size.0 {
                 Some(height) => height,
-                None => panic!("Failed to get model height. Make it explicit with `--height`"),
+                None => bail!("Failed to get model height. Make it explicit with `--height`"),
             }
         } else {
             inputs.shapes[0][2] as u32
@@ -133,7 +138,7 @@ impl OrtBackend {
         let width = if inputs.shapes[0][3] == -1 {
             match args.image_size.1 {
                 Some(width) => width,
-                None => panic!("Failed to get model width. Make it explicit with `--width`"),
+                None => bail!("Failed to get model width. Make it explicit with `--width`"),
             }
         } else {
             inputs.shapes[0][3] as u32
@@ -141,37 +146,44 @@ impl OrtBackend {
         inputs.sizes.push(vec![height, width]);
 
         // build provider
-        let (ep, provider) = match args.ep {
+        let (mut ep, provider) = match args.ep {
             OrtEP::Cuda(device_id) => Self::set_ep_cuda(device_id),
             OrtEP::Trt(device_id) => Self::set_ep_trt(device_id, args.trt_fp16, &batch, &inputs),
             _ => (OrtEP::Cpu, ExecutionProvider::CPU(Default::default())),
         };
 
-        // build session again with the new provider
-        let session = SessionBuilder::new(&env)?
-            // .with_optimization_level(ort::GraphOptimizationLevel::Level3)?
-            .with_execution_providers([provider])?
-            .with_model_from_file(args.f)?;
+        // build session again with the new provider, the GPU providers can still fail at this point
+        let session = match Self::build_session(&env, provider, args.threads, &args.f) {
+            Ok(session) => session,
+            Err(e) if ep != OrtEP::Cpu => {
+                warn!("Failed to create session with {:?} ({}), falling back to CPU", ep, e);
+                ep = OrtEP::Cpu;
+                Self::build_session(&env, ExecutionProvider::CPU(Default::default()), args.threads, &args.f)?
+            }
+            Err(e) => return Err(e),
+        };
+
+        // on CPU, dynamic models run at a reduced resolution to keep up with the cameras
+        if ep == OrtEP::Cpu {
+            if let Some(size) = args.cpu_image_size {
+                let (height, width) = (inputs.sizes[0][0], inputs.sizes[0][1]);
+                inputs.sizes[0] = vec![
+                    if inputs.shapes[0][2] == -1 { size } else { height },
+                    if inputs.shapes[0][3] == -1 { size } else { width },
+                ];
+                if inputs.sizes[0] != [size, size] {
+                    warn!(
+                        "{} has a fixed input size and runs at {:?} instead of {}x{}, export it with dynamic height and width",
+                        args.f, inputs.sizes[0], size, size
+                    );
+                }
+            }
+        }
 
         // task: using given one or guessing
         let task = match args.task {
             Some(task) => task,
-            None => match session.metadata() {
-                Err(_) => panic!("No metadata found. Try making it explicit by `--task`"),
-                Ok(metadata) => match metadata.custom("task") {
-                    Err(_) => panic!("Can not get custom value. Try making it explicit by `--task`"),
-                    Ok(value) => match value {
-                        None => panic!("No corresponding value of `task` found in metadata. Make it explicit by `--task`"),
-                        Some(task) => match task.as_str() {
-                            "classify" => YOLOTask::Classify,
-                            "detect" => YOLOTask::Detect,
-                            "pose" => YOLOTask::Pose,
-                            "segment" => YOLOTask::Segment,
-                            x => todo!("{:?} is not supported for now!", x),
-                        },
-                    },
-                },
-            },
+            None => Self::task_from_session(&session)?,
         };
 
         Ok(Self {
@@ -183,6 +195,63 @@ impl OrtBackend {
         })
     }
 
+    /// Opens a model with the default CPU session, just far enough to read its inputs and metadata
+    pub fn inspect(f: &str) -> Result<Self> {
+        let env = Environment::builder()
+            .with_name("YOLOv8")
+            .build()?
+            .into_arc();
+        let session = SessionBuilder::new(&env)?.with_model_from_file(f)?;
+
+        let mut inputs = OrtInputs::new(&session);
+        Self::check_inputs(&inputs)?;
+        inputs.sizes.push(vec![
+            inputs.shapes[0][2].max(0) as u32,
+            inputs.shapes[0][3].max(0) as u32,
+        ]);
+
+        let task = Self::task_from_session(&session)?;
+
+        Ok(Self {
+            session,
+            task,
+            ep: OrtEP::Cpu,
+            batch: Batch::default(),
+            inputs,
+        })
+    }
+
+    fn check_inputs(inputs: &OrtInputs) -> Result<()> {
+        match inputs.shapes.first() {
+            Some(shape) if shape.len() == 4 => Ok(()),
+            _ => bail!("Expected an NCHW image input, got {:?}", inputs.shapes),
+        }
+    }
+
+    fn task_from_session(session: &Session) -> Result<YOLOTask> {
+        match Self::metadata_value(session, "task").as_deref() {
+            Some("classify") => Ok(YOLOTask::Classify),
+            Some("detect") => Ok(YOLOTask::Detect),
+            Some("pose") => Ok(YOLOTask::Pose),
+            Some("segment") => Ok(YOLOTask::Segment),
+            Some(x) => bail!("{:?} is not supported for now!", x),
+            None => bail!("No corresponding value of `task` found in metadata. Make it explicit by `--task`"),
+        }
+    }
+
+    fn build_session(env: &Arc<Environment>, provider: ExecutionProvider, threads: u16, f: &str) -> Result<Session> {
+        let mut builder = SessionBuilder::new(env)?
+            // .with_optimization_level(ort::GraphOptimizationLevel::Level3)?
+            .with_execution_providers([provider])?;
+
+        // 0 leaves the thread count up to ORT
+        if threads > 0 {
+            builder = builder.with_intra_threads(threads as i16)?;
+        }
+
+        Ok(builder.with_model_from_file(f)?)
+    }
+
     pub fn fetch_inputs_from_session(
         session: &Session,
     ) -> (Vec<Vec<i32>>, Vec<TensorElementDataType>, Vec<String>) {
@@ -269,9 +338,9 @@ impl OrtBackend {
         }
     }
 
-    pub fn fetch_from_metadata(&self, key: &str) -> Option<String> {
+    fn metadata_value(session: &Session, key: &str) -> Option<String> {
         // fetch value from onnx model file by key
-        match self.session.metadata() {
+        match session.metadata() {
             Err(_) => None,
             Ok(metadata) => match metadata.custom(key) {
                 Err(_) => None,
@@ -280,6 +349,18 @@ impl OrtBackend {
         }
     }
 
+    pub fn fetch_from_metadata(&self, key: &str) -> Option<String> {
+        Self::metadata_value(&self.session, key)
+    }
+
+    pub fn image_size(&self) -> Option<(u32, u32)> {
+        // image size, metadata parsing: String `imgsz` in onnx model: `[640, 640]`
+        let imgsz = self.fetch_from_metadata("imgsz")?;
+        let re = Regex::new(r"([0-9]+), ([0-9]+)").unwrap();
+        let caps = re.captures(&imgsz)?;
+        Some((caps.get(1)?.as_str().parse().ok()?, caps.get(2)?.as_str().parse().ok()?))
+    }
+
     pub fn run(&self, xs: Array<f32, IxDyn>, profile: bool) -> Result<Vec<Array<f32, IxDyn>>> {
         // ORT inference
         match self.dtype() {
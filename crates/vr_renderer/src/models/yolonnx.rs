@@ -1,120 +1,133 @@
+use std::path::Path;
+use std::time::{Duration, Instant};
+use anyhow::{bail, Result};
 use image::{DynamicImage, GrayImage};
-use tracing::{debug, trace};
+use tracing::{debug, error, trace, warn};
 use messages::ModelConfiguration;
-use crate::models::SegmentationModel;
-use crate::models::yolo::{Args, OrtEP, YOLOResult, YOLO};
+use crate::models::{Instance, SegmentationModel, StageTimings};
+use crate::models::yolo::{Args, OrtEP, YOLOResult, YOLOTask, YOLO};
 
-pub enum YoloV8ONNXQuantization {
-    Int8,
-    Half,
-    None,
-}
-
-pub enum YoloV8ONNXModelType {
-    V8m(YoloV8ONNXQuantization),
-    V11s(YoloV8ONNXQuantization),
-    V11m(YoloV8ONNXQuantization),
-}
-
-impl YoloV8ONNXQuantization {
-    fn to_model_pathspec(&self) -> String {
-        match self {
-            YoloV8ONNXQuantization::Int8 => {
-                "-int8".to_string()
-            }
-            YoloV8ONNXQuantization::Half => {
-                "-half".to_string()
-            }
-            YoloV8ONNXQuantization::None => {
-                "".to_string()
-            }
-        }
-    }
-}
-
-
-impl YoloV8ONNXModelType {
-    fn to_model_path(&self) -> String {
-        match self {
-            YoloV8ONNXModelType::V8m(q) => { format!("./onnx/yolov8m-seg{}.onnx", q.to_model_pathspec()).to_string() }
-            YoloV8ONNXModelType::V11s(q) => { format!("./onnx/yolo11s-seg{}.onnx", q.to_model_pathspec()).to_string() }
-            YoloV8ONNXModelType::V11m(q) => { format!("./onnx/yolo11m-seg{}.onnx", q.to_model_pathspec()).to_string() }
-        }
-    }
-}
 pub struct YoloONNXSegmentationModel {
     model: YOLO,
+    name: String,
     last_masks: Vec<GrayImage>,
+    /// Turning the model's results into instances, on top of the model's own postprocessing
+    conversion: Duration,
 }
 
 impl YoloONNXSegmentationModel {
-    pub fn new(mtype: YoloV8ONNXModelType, model_configuration: &ModelConfiguration) -> Self {
-        let yolo = YOLO::new(Args::new(mtype.to_model_path(), &model_configuration)).unwrap();
+    pub fn new(path: &Path, model_configuration: &ModelConfiguration) -> Result<Self> {
+        let yolo = YOLO::new(Args::new(path.to_string_lossy().to_string(), &model_configuration))?;
+        if *yolo.task() != YOLOTask::Segment {
+            bail!("{} is a {:?} model, hand masking needs a segmentation model", path.display(), yolo.task());
+        }
+        if !model_configuration.classes.is_empty() && yolo.class_conf().iter().all(Option::is_none) {
+            bail!(
+                "None of the mask classes {:?} exist in {}, it knows {:?}",
+                model_configuration.classes.iter().map(|it| &it.name).collect::<Vec<_>>(),
+                path.display(),
+                yolo.names(),
+            );
+        }
+
+        for class in &model_configuration.classes {
+            if !yolo.names().contains(&class.name) {
+                warn!("Mask class {} does not exist in {}", class.name, path.display());
+            }
+        }
         yolo.summary();
 
-        Self {
+        Ok(Self {
             model: yolo,
+            name: path.file_name().map_or(String::new(), |it| it.to_string_lossy().to_string()),
             last_masks: vec![],
-        }
+            conversion: Duration::ZERO,
+        })
     }
 }
 
 impl SegmentationModel for YoloONNXSegmentationModel {
-    fn predict(&mut self, images: &Vec<DynamicImage>) -> Vec<Option<GrayImage>> {
+    fn predict(&mut self, images: &Vec<DynamicImage>) -> Vec<Option<Vec<Instance>>> {
         let _span = tracing::debug_span!("YoloONNXSegmentationModel::predict");
-        let images = images.iter()
-            .map(|it|
-                it.resize(
-                    640, 640,
-                    image::imageops::FilterType::Nearest,
-                )
-            ).collect::<Vec<DynamicImage>>();
-        debug!("Resized images");
-
-        let ys_result = self.model.run(&images).unwrap();
+        let ys_result = match self.model.run(images) {
+            Ok(result) => result,
+            Err(e) => {
+                error!("Inference failed: {:?}", e);
+                return vec![None; images.len()];
+            }
+        };
 
         debug!("Got result");
 
+        let started = Instant::now();
         let result = ys_result
             .iter()
             .zip(images.iter())
             .map(|(result, img)| {
                 let _span = tracing::debug_span!("YoloONNXSegmentationModel::postprocess");
-                if result.bboxes.is_none() || result.masks.is_none() {
-                    return None;
-                }
+                let (Some(bboxes), Some(masks)) = (result.bboxes(), result.masks()) else {
+                    return Some(vec![]);
+                };
 
                 debug!("Got bboxes and masks");
 
-                let masks = result.masks.clone().unwrap();
-                debug!("Cloned masks");
-
-                // let mut final_mask = GrayImage::new(img.width(), img.height());
-                let mut mask_data = vec![0; (img.width() * img.height()) as usize];
+                let len = (img.width() * img.height()) as usize;
+                let names = self.model.names();
 
-                debug!("Created final mask");
+                // bboxes and masks are pushed in the same order
+                let instances = bboxes.iter().zip(masks.iter()).map(|(bbox, mask)| {
+                    let mut mask_data = vec![0; len];
+                    let copied = mask.len().min(len);
+                    mask_data[..copied].copy_from_slice(&mask[..copied]);
 
-                for mask in masks.iter() {
-                    for (id, px) in mask.iter().enumerate() {
-
-                        if *px != 0 && id < mask_data.len() {
-                            mask_data[id] = 255;
-                        }
+                    Instance {
+                        name: names.get(bbox.id()).cloned().unwrap_or_default(),
+                        confidence: bbox.confidence(),
+                        bbox: bbox.clone(),
+                        mask: GrayImage::from_raw(img.width(), img.height(), mask_data).unwrap(),
                     }
-                }
-
-                let final_mask = GrayImage::from_raw(img.width(), img.height(), mask_data).unwrap();
+                }).collect();
 
                 debug!("Processed masks");
-                Some(final_mask)
+                Some(instances)
             }).collect();
 
+        self.conversion = started.elapsed();
         debug!("Returning result");
 
         result
     }
 
+    fn warm_up(&mut self) -> Result<()> {
+        let images = vec![DynamicImage::new_rgb8(self.model.width(), self.model.height()); self.model.batch() as usize];
+        let results = self.model.run(&images)?;
+        if results.len() != images.len() {
+            bail!("Warm-up returned {} results for {} images", results.len(), images.len());
+        }
+        Ok(())
+    }
+
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn timings(&self) -> StageTimings {
+        let timings = self.model.timings();
+        StageTimings {
+            postprocess: timings.postprocess + self.conversion,
+            ..timings
+        }
+    }
+
     fn is_gpu(&self) -> bool {
         !matches!(self.model.engine().ep(), OrtEP::Cpu)
     }
+
+    fn execution_provider(&self) -> String {
+        format!("{:?}", self.model.engine().ep())
+    }
+
+    fn input_size(&self) -> (u32, u32) {
+        (self.model.width(), self.model.height())
+    }
 }
\ No newline at end of file
@@ -0,0 +1,65 @@
+use std::time::Instant;
+use ggez::glam::Vec2;
+use messages::Reprojection;
+use crate::inference::HeadOrientation;
+
+/// Follows the camera servos from the positions the car was told to turn them to. The head is
+/// usually ahead of them, the car writes new positions every 100ms and the servos need time to
+/// travel, so the camera image is shifted by the difference like a timewarp would.
+pub struct CameraTracker {
+    settings: Reprojection,
+    commanded: Option<HeadOrientation>,
+    camera: HeadOrientation,
+    last_update: Instant,
+}
+
+impl CameraTracker {
+    pub fn new(settings: &Reprojection) -> Self {
+        Self {
+            settings: settings.clone(),
+            commanded: None,
+            camera: HeadOrientation::default(),
+            last_update: Instant::now(),
+        }
+    }
+
+    pub fn configure(&mut self, settings: &Reprojection) {
+        self.settings = settings.clone();
+    }
+
+    pub fn command(&mut self, target: HeadOrientation) {
+        if self.commanded.is_none() {
+            self.camera = target;
+        }
+        self.commanded = Some(target);
+    }
+
+    /// Moves the estimated camera angles towards the last command at servo speed
+    pub fn update(&mut self) {
+        let elapsed = self.last_update.elapsed().as_secs_f32();
+        self.last_update = Instant::now();
+
+        let Some(target) = self.commanded else {
+            return;
+        };
+        let step = self.settings.servo_speed.max(0.0) * elapsed;
+        let approach = |from: f32, to: f32| from + (to - from).clamp(-step, step);
+        self.camera = HeadOrientation {
+            yaw: approach(self.camera.yaw, target.yaw),
+            pitch: approach(self.camera.pitch, target.pitch),
+        };
+    }
+
+    /// Pixel offset for the camera images, nothing until the car reported a servo position
+    pub fn offset(&self, head: HeadOrientation, pixels_per_radian: f32) -> Vec2 {
+        if !self.settings.enabled || self.commanded.is_none() {
+            return Vec2::ZERO;
+        }
+
+        let scale = self.settings.strength * pixels_per_radian;
+        Vec2::new(
+            -(head.yaw - self.camera.yaw) * scale,
+            -(head.pitch - self.camera.pitch) * scale,
+        )
+    }
+}
@@ -1,14 +1,23 @@
+use std::collections::HashMap;
 use std::fmt::{Debug, Formatter};
-use crate::models::{load_model, SegmentationModel};
+use std::time::{Duration, Instant};
+use crate::models::{Instance, SegmentationModel, StageTimings};
+use crate::stabilizer::MaskStabilizer;
 use image::{DynamicImage, GenericImageView, GrayImage, ImageBuffer, Rgba, RgbaImage};
-use messages::file_config::read_config;
 use itertools::izip;
 use tracing::{debug_span, instrument, trace, trace_span};
-use messages::RenderSettingsData;
+use messages::{Detection, DetectionFrame, MaskShaping, MaskSmoothing, ModelConfiguration, RenderSettingsData};
 
 pub struct SegmentationCache {
-    model: Box<dyn SegmentationModel>,
-    last_masks: Vec<GrayImage>,
+    model: Option<Box<dyn SegmentationModel>>,
+    stabilizer: MaskStabilizer,
+    class_alpha: HashMap<String, u8>,
+    detections: Vec<DetectionFrame>,
+    shaping: MaskShaping,
+    /// Composing, smoothing and shaping the masks of the last frames
+    mask_time: Duration,
+    /// Maps the model's confidence to alpha according to `shaping.softness`
+    edge: [u8; 256],
 }
 
 impl Debug for SegmentationCache {
@@ -18,92 +27,203 @@ impl Debug for SegmentationCache {
 }
 
 impl SegmentationCache {
-    pub fn new() -> Self {
-        let config = read_config();
-
+    pub fn new(settings: &RenderSettingsData) -> Self {
         Self {
-            model: load_model(&config),
-            last_masks: vec![],
+            model: None,
+            stabilizer: MaskStabilizer::new(&settings.mask_smoothing),
+            class_alpha: HashMap::new(),
+            detections: vec![],
+            shaping: settings.mask_shaping.clone(),
+            mask_time: Duration::ZERO,
+            edge: Self::edge_curve(settings.mask_shaping.softness),
+        }
+    }
+
+    fn edge_curve(softness: f32) -> [u8; 256] {
+        let softness = softness.clamp(0.0, 1.0);
+        let mut curve = [0; 256];
+        for (value, alpha) in curve.iter_mut().enumerate() {
+            let probability = value as f32 / 255.0;
+            *alpha = if softness == 0.0 {
+                if probability >= 0.5 { 255 } else { 0 }
+            } else {
+                (((probability - (0.5 - softness / 2.0)) / softness).clamp(0.0, 1.0) * 255.0) as u8
+            };
         }
+        curve
     }
 
-    pub fn reload(&mut self, config: &RenderSettingsData) {
-        self.model = load_model(&config)
+    pub fn set_smoothing(&mut self, settings: &MaskSmoothing) {
+        self.stabilizer.configure(settings);
+    }
+
+    /// Masks of the previous model are dropped, they may not match what the new one detects
+    pub fn swap(&mut self, model: Box<dyn SegmentationModel>, config: &ModelConfiguration) {
+        self.model = Some(model);
+        self.stabilizer.reset();
+        self.class_alpha = config.classes
+            .iter()
+            .map(|class| (class.name.clone(), (class.opacity.clamp(0.0, 1.0) * 255.0) as u8))
+            .collect();
     }
 
     fn apply_mask(image: &DynamicImage, mask: &GrayImage) -> DynamicImage {
         let _span = debug_span!("apply_mask").entered();
         let mut overlay = image.clone().to_rgba8();
+        // the mask already carries the per-class opacity, so it is used as alpha directly
         overlay.pixels_mut().zip(mask.pixels()).for_each(|(pixel, mask_pixel)| {
-            let mask_pixel = mask_pixel[0];
-            pixel.0[3] = if mask_pixel > 150 {
-                255
-            } else {
-                mask_pixel
-            };
+            pixel.0[3] = mask_pixel[0];
         });
         overlay.into()
     }
 
-    fn mask_postprocess(mask: GrayImage) -> GrayImage {
+    /// Draws every instance with the opacity of its class, overlapping instances keep the most opaque one
+    fn compose(&self, width: u32, height: u32, instances: &[Instance]) -> GrayImage {
+        let _span = debug_span!("compose_instances").entered();
+        let mut mask = GrayImage::new(width, height);
+        for instance in instances {
+            if instance.mask.dimensions() != (width, height) {
+                continue;
+            }
+            let alpha = self.class_alpha.get(&instance.name).copied().unwrap_or(255) as u16;
+            mask.pixels_mut().zip(instance.mask.pixels()).for_each(|(pixel, instance_pixel)| {
+                let value = (self.edge[instance_pixel.0[0] as usize] as u16 * alpha / 255) as u8;
+                pixel.0[0] = pixel.0[0].max(value);
+            });
+        }
+        mask
+    }
+
+    fn mask_postprocess(&self, mask: GrayImage) -> GrayImage {
         let _span = debug_span!("mask_postprocess").entered();
+        let mask = match self.shaping.dilation {
+            0 => mask,
+            dilation => Self::morph(&mask, dilation.unsigned_abs(), dilation > 0),
+        };
+
         // Blur edges of mask to avoid sharp transitions
-        imageproc::filter::gaussian_blur_f32(&mask, 2.0)
+        if self.shaping.feather > 0.0 {
+            imageproc::filter::gaussian_blur_f32(&mask, self.shaping.feather)
+        } else {
+            mask
+        }
+    }
+
+    /// Grayscale dilation (`grow`) or erosion over a square of `radius`, done in two passes so soft
+    /// edges keep their gradient instead of being binarized like `imageproc::morphology` does
+    fn morph(mask: &GrayImage, radius: u32, grow: bool) -> GrayImage {
+        let _span = debug_span!("morph").entered();
+        let pick = |a: u8, b: u8| if grow { a.max(b) } else { a.min(b) };
+        let (width, height) = mask.dimensions();
+        let radius = radius as i64;
+
+        let pass = |source: &GrayImage, horizontal: bool| {
+            GrayImage::from_fn(width, height, |x, y| {
+                let (x, y) = (x as i64, y as i64);
+                let mut value = source.get_pixel(x as u32, y as u32).0[0];
+                for offset in -radius..=radius {
+                    let (nx, ny) = if horizontal { (x + offset, y) } else { (x, y + offset) };
+                    if nx >= 0 && ny >= 0 && nx < width as i64 && ny < height as i64 {
+                        value = pick(value, source.get_pixel(nx as u32, ny as u32).0[0]);
+                    }
+                }
+                image::Luma([value])
+            })
+        };
+
+        pass(&pass(mask, true), false)
     }
 
-    pub fn segment(&mut self, hand: Vec<&DynamicImage>, re_segment: bool) -> Vec<DynamicImage> {
-        let _span = debug_span!("segment_merge").entered();
-        let sized_hand = debug_span!("sized_hand").in_scope(|| hand
+    pub fn masks(&mut self, hands: &Vec<DynamicImage>) -> Vec<GrayImage> {
+        let _span = debug_span!("segment_masks").entered();
+        let Some(model) = self.model.as_mut() else {
+            return vec![];
+        };
+
+        let predictions = debug_span!("predict").in_scope(|| model.predict(hands));
+
+        self.detections = predictions
             .iter()
-            .map(
-                |it| it.resize(
-                    640, 640,
-                    image::imageops::FilterType::Nearest,
-                )
-            ));
-
-        let hands = debug_span!("collect_hands").in_scope(|| sized_hand.collect());
-        let masks = debug_span!("predict").in_scope(|| {
-            if re_segment || self.last_masks.len() != hand.len() {
-                self.model.predict(&hands)
-                    .iter()
-                    .enumerate()
-                    .map(|(id, optional_mask)| {
-                        if let Some(mask) = optional_mask {
-                            let mask = mask.clone();
-                            if self.last_masks.len() > id {
-                                self.last_masks[id] = mask.clone();
-                            } else {
-                                self.last_masks.push(mask.clone());
-                            }
-                            mask
-                        } else {
-                            if self.last_masks.len() > id {
-                                self.last_masks[id].clone()
-                            } else {
-                                GrayImage::new(1, 1)
-                            }
-                        }
-                    }).collect()
-            } else {
-                self.last_masks.clone()
-            }
-        });
+            .zip(hands.iter())
+            .map(|(instances, hand)| DetectionFrame {
+                width: hand.width(),
+                height: hand.height(),
+                detections: instances.iter().flatten().map(|instance| Detection {
+                    class: instance.name.clone(),
+                    confidence: instance.confidence,
+                    bbox: (instance.bbox.xmin(), instance.bbox.ymin(), instance.bbox.width(), instance.bbox.height()),
+                }).collect(),
+            })
+            .collect();
+
+        let started = Instant::now();
+        let masks = predictions
+            .iter()
+            .zip(hands.iter())
+            .map(|(instances, hand)| instances
+                .as_ref()
+                .map(|instances| self.compose(hand.width(), hand.height(), instances)))
+            .collect();
+        let masks = self.stabilizer.stabilize(masks);
 
-        debug_span!("apply_masks").in_scope(|| masks
+        let masks = debug_span!("mask_postprocess").in_scope(|| masks
             .into_iter()
+            .map(|mask| self.mask_postprocess(mask))
+            .collect()
+        );
+        self.mask_time = started.elapsed();
+        masks
+    }
+
+    /// What the model found in the last frames passed to [`Self::masks`]
+    pub fn detections(&self) -> &Vec<DetectionFrame> {
+        &self.detections
+    }
+
+    pub fn shift_mask(mask: &GrayImage, offset: (i32, i32)) -> GrayImage {
+        let _span = debug_span!("shift_mask").entered();
+        if offset == (0, 0) {
+            return mask.clone();
+        }
+        imageproc::geometric_transformations::translate(mask, offset)
+    }
+
+    pub fn apply_masks(hands: &[DynamicImage], masks: &[GrayImage]) -> Vec<DynamicImage> {
+        let _span = debug_span!("apply_masks").entered();
+        masks
+            .iter()
             .zip(hands.iter())
-            .map(
-                |(mask, hand)|
-                    Self::apply_mask(
-                        &hand,
-                        &Self::mask_postprocess(mask),
-                    )
-            ).collect()
-        )
+            .map(|(mask, hand)| {
+                if mask.dimensions() != hand.dimensions() {
+                    return DynamicImage::new_rgba8(hand.width(), hand.height());
+                }
+                Self::apply_mask(hand, mask)
+            })
+            .collect()
+    }
+
+    /// Postprocessing includes the mask work done here after the model
+    pub fn timings(&self) -> StageTimings {
+        let timings = self.model.as_ref().map_or(StageTimings::default(), |it| it.timings());
+        StageTimings {
+            postprocess: timings.postprocess + self.mask_time,
+            ..timings
+        }
+    }
+
+    pub fn model_name(&self) -> String {
+        self.model.as_ref().map_or("None".to_string(), |it| it.name())
     }
 
     pub fn is_gpu(&self) -> bool {
-        self.model.is_gpu()
+        self.model.as_ref().is_some_and(|it| it.is_gpu())
+    }
+
+    pub fn execution_provider(&self) -> String {
+        self.model.as_ref().map_or("None".to_string(), |it| it.execution_provider())
+    }
+
+    pub fn input_size(&self) -> (u32, u32) {
+        self.model.as_ref().map_or((0, 0), |it| it.input_size())
     }
 }
@@ -0,0 +1,53 @@
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+    @location(1) color: vec4<f32>,
+}
+
+struct Lens {
+    k1: f32,
+    k2: f32,
+    center_x: f32,
+    center_y: f32,
+    aspect: f32,
+    scale: f32,
+    red: f32,
+    green: f32,
+    blue: f32,
+}
+
+@group(1) @binding(0)
+var t: texture_2d<f32>;
+
+@group(1) @binding(1)
+var s: sampler;
+
+@group(3) @binding(0)
+var<uniform> lens: Lens;
+
+// Where the pixel at `uv` has to be sampled from so the lens bends it back into place
+fn distort(uv: vec2<f32>, channel_scale: f32) -> vec2<f32> {
+    let center = vec2<f32>(lens.center_x, lens.center_y);
+    let offset = uv - center;
+    // normalized so the shorter half of the viewport has radius 1
+    let normalized = offset * 2.0 * vec2<f32>(max(lens.aspect, 1.0), max(1.0 / lens.aspect, 1.0));
+    let r2 = dot(normalized, normalized);
+    let factor = (1.0 + lens.k1 * r2 + lens.k2 * r2 * r2) * channel_scale / lens.scale;
+    return center + offset * factor;
+}
+
+// Outside the eye's image is black instead of a smeared edge
+fn sample_channel(uv: vec2<f32>) -> vec4<f32> {
+    // sampled before the check, textureSample has to stay in uniform control flow
+    let color = textureSample(t, s, uv);
+    let inside = all(uv >= vec2<f32>(0.0)) && all(uv <= vec2<f32>(1.0));
+    return select(vec4<f32>(0.0, 0.0, 0.0, 1.0), color, inside);
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let red = sample_channel(distort(in.uv, lens.red));
+    let green = sample_channel(distort(in.uv, lens.green));
+    let blue = sample_channel(distort(in.uv, lens.blue));
+    return in.color * vec4<f32>(red.r, green.g, blue.b, 1.0);
+}
@@ -0,0 +1,333 @@
+use std::io::{Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{sync_channel, Receiver, SyncSender, TryRecvError};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+use ggez::{Context, GameResult};
+use ggez::glam::Vec2;
+use ggez::graphics::{Canvas, Color, DrawParam, Image, ImageFormat};
+use image::codecs::jpeg::JpegEncoder;
+use image::imageops::{self, FilterType};
+use image::{ColorType, DynamicImage, RgbaImage};
+use log::{info, warn};
+use pub_sub::PubSub;
+use messages::{LogMessageType, SpectatorConfig, SpectatorView, VrMessage};
+
+const BOUNDARY: &str = "spectatorframe";
+/// A client that does not take a frame for this long is dropped, so it does not hold a slot forever
+const WRITE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Most recent encoded frame, `sequence` lets clients wait for the next one
+#[derive(Default)]
+struct Frame {
+    sequence: u64,
+    jpeg: Arc<Vec<u8>>,
+}
+
+type SharedFrame = Arc<(Mutex<Frame>, Condvar)>;
+
+/// A captured frame on its way back from the GPU. The copy is recorded into the frame being drawn,
+/// mapping starts once that frame was submitted and the pixels are picked up when they are there.
+struct Readback {
+    buffer: wgpu::Buffer,
+    width: u32,
+    height: u32,
+    /// `None` while the frame with the copy was not submitted yet
+    mapped: Option<Receiver<Result<(), wgpu::BufferAsyncError>>>,
+}
+
+/// Copies the eye targets into a small image at `max_fps` and streams it as MJPEG. The readback
+/// is asynchronous and encoding and serving happen on their own threads, so the headset never
+/// waits for the stream. Nothing is captured while nobody watches.
+pub struct Spectator {
+    settings: SpectatorConfig,
+    target: Option<Image>,
+    readback: Option<Readback>,
+    last_capture: Instant,
+    frames: SyncSender<RgbaImage>,
+    clients: Arc<AtomicUsize>,
+}
+
+impl Spectator {
+    pub fn new(settings: &SpectatorConfig, bus: PubSub<VrMessage>) -> Option<Self> {
+        if !settings.enabled {
+            return None;
+        }
+
+        let listener = match TcpListener::bind(&settings.address) {
+            Ok(listener) => listener,
+            Err(e) => {
+                let _ = bus.send(VrMessage::Log {
+                    message: format!("Failed to start the spectator stream on {}: {}", settings.address, e),
+                    message_type: LogMessageType::Error,
+                });
+                return None;
+            }
+        };
+        info!("Spectator stream on http://{}/", settings.address);
+
+        let shared: SharedFrame = Arc::default();
+        let (frames, inbox) = sync_channel(1);
+
+        let quality = settings.quality.clamp(1, 100);
+        let encoder_frame = shared.clone();
+        let encoder = thread::Builder::new()
+            .name("spectator-encoder".to_string())
+            .spawn(move || encode(inbox, quality, encoder_frame));
+        let clients = Arc::new(AtomicUsize::new(0));
+        let server_clients = clients.clone();
+        let max_clients = settings.max_clients;
+        let server = thread::Builder::new()
+            .name("spectator-server".to_string())
+            .spawn(move || serve(listener, shared, server_clients, max_clients));
+        if encoder.is_err() || server.is_err() {
+            warn!("Failed to spawn the spectator threads");
+            return None;
+        }
+
+        Some(Spectator {
+            settings: settings.clone(),
+            target: None,
+            readback: None,
+            last_capture: Instant::now(),
+            frames,
+            clients,
+        })
+    }
+
+    fn watched(&self) -> bool {
+        self.clients.load(Ordering::Relaxed) > 0
+    }
+
+    /// Limits captures to `max_fps`
+    fn due(&mut self) -> bool {
+        let interval = Duration::from_secs_f32(1.0 / self.settings.max_fps.max(1) as f32);
+        if self.last_capture.elapsed() < interval {
+            return false;
+        }
+        self.last_capture = Instant::now();
+        true
+    }
+
+    /// Draws the configured view of both eye targets into the stream image and starts reading it back,
+    /// finished readbacks are handed to the encoder. Call it every frame, after the eye targets are drawn.
+    pub fn capture(&mut self, ctx: &mut Context, left: &Image, right: &Image) -> GameResult {
+        if let Some(readback) = self.readback.take() {
+            self.readback = self.collect(ctx, readback);
+        }
+        if self.readback.is_some() || !self.watched() || !self.due() {
+            return Ok(());
+        }
+
+        let eyes = if self.settings.view == SpectatorView::Both { 2 } else { 1 };
+        // readbacks need rows of a multiple of 256 bytes
+        let width = self.settings.width.max(64).div_ceil(64) * 64;
+        let height = (width as f32 * left.height() as f32 / (left.width() * eyes).max(1) as f32).round().max(1.0) as u32;
+
+        let target = match &self.target {
+            Some(target) if target.width() == width && target.height() == height => target.clone(),
+            _ => {
+                let target = Image::new_canvas_image(ctx, ImageFormat::Rgba8UnormSrgb, width, height, 1);
+                self.target = Some(target.clone());
+                target
+            }
+        };
+
+        let mut canvas = Canvas::from_image(ctx, target.clone(), Color::BLACK);
+        let eye_width = width as f32 / eyes as f32;
+        let draw = |canvas: &mut Canvas, image: &Image, x: f32| canvas.draw(
+            image,
+            DrawParam::default()
+                .dest(Vec2::new(x, 0.0))
+                .scale(Vec2::new(eye_width / image.width() as f32, height as f32 / image.height() as f32)),
+        );
+        match self.settings.view {
+            SpectatorView::Left => draw(&mut canvas, left, 0.0),
+            SpectatorView::Right => draw(&mut canvas, right, 0.0),
+            SpectatorView::Both => {
+                draw(&mut canvas, left, 0.0);
+                draw(&mut canvas, right, eye_width);
+            }
+        }
+        canvas.finish(ctx)?;
+
+        self.readback = Self::copy(ctx, &target);
+        Ok(())
+    }
+
+    /// Records copying `target` into a buffer into the frame's commands, they run after the canvas
+    fn copy(ctx: &mut Context, target: &Image) -> Option<Readback> {
+        let (width, height) = (target.width(), target.height());
+        let buffer = ctx.gfx.wgpu().device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("spectator readback"),
+            size: 4 * width as u64 * height as u64,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+
+        ctx.gfx.commands()?.copy_texture_to_buffer(
+            target.wgpu().0.as_image_copy(),
+            wgpu::ImageCopyBuffer {
+                buffer: &buffer,
+                layout: wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: Some(4 * width),
+                    rows_per_image: None,
+                },
+            },
+            wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+        );
+
+        Some(Readback { buffer, width, height, mapped: None })
+    }
+
+    /// Moves a readback along without waiting, `None` once it is done with
+    fn collect(&self, ctx: &Context, mut readback: Readback) -> Option<Readback> {
+        let Some(mapped) = &readback.mapped else {
+            // the frame with the copy was submitted by now
+            let (sender, mapped) = sync_channel(1);
+            readback.buffer.slice(..).map_async(wgpu::MapMode::Read, move |result| {
+                let _ = sender.send(result);
+            });
+            readback.mapped = Some(mapped);
+            return Some(readback);
+        };
+
+        ctx.gfx.wgpu().device.poll(wgpu::Maintain::Poll);
+        match mapped.try_recv() {
+            Err(TryRecvError::Empty) => Some(readback),
+            Err(TryRecvError::Disconnected) => None,
+            Ok(Err(e)) => {
+                warn!("Failed to read back the spectator frame: {}", e);
+                None
+            }
+            Ok(Ok(())) => {
+                let pixels = readback.buffer.slice(..).get_mapped_range().to_vec();
+                readback.buffer.unmap();
+                if let Some(frame) = RgbaImage::from_raw(readback.width, readback.height, pixels) {
+                    // the encoder is still busy with the last frame, spectators can do with fewer frames
+                    let _ = self.frames.try_send(frame);
+                }
+                None
+            }
+        }
+    }
+
+    /// Like `capture`, for the headless mode that composes the eyes on the CPU
+    pub fn capture_images(&mut self, left: &DynamicImage, right: &DynamicImage) {
+        if !self.watched() || !self.due() {
+            return;
+        }
+
+        let eyes = if self.settings.view == SpectatorView::Both { 2 } else { 1 };
+        let width = self.settings.width.max(64);
+        let height = (width as f32 * left.height() as f32 / (left.width() * eyes).max(1) as f32).round().max(1.0) as u32;
+        let eye_width = width / eyes;
+
+        let mut frame = RgbaImage::new(width, height);
+        let mut place = |image: &DynamicImage, x: u32| {
+            let scaled = image.resize_exact(eye_width, height, FilterType::Triangle).to_rgba8();
+            imageops::replace(&mut frame, &scaled, x as i64, 0);
+        };
+        match self.settings.view {
+            SpectatorView::Left => place(left, 0),
+            SpectatorView::Right => place(right, 0),
+            SpectatorView::Both => {
+                place(left, 0);
+                place(right, eye_width);
+            }
+        }
+
+        let _ = self.frames.try_send(frame);
+    }
+}
+
+fn encode(inbox: Receiver<RgbaImage>, quality: u8, shared: SharedFrame) {
+    for frame in inbox {
+        let rgb = image::DynamicImage::ImageRgba8(frame).to_rgb8();
+        let mut jpeg = vec![];
+        if let Err(e) = JpegEncoder::new_with_quality(&mut jpeg, quality)
+            .encode(rgb.as_raw(), rgb.width(), rgb.height(), ColorType::Rgb8) {
+            warn!("Failed to encode spectator frame: {}", e);
+            continue;
+        }
+
+        let (frame, changed) = &*shared;
+        let mut frame = frame.lock().unwrap();
+        frame.sequence += 1;
+        frame.jpeg = Arc::new(jpeg);
+        changed.notify_all();
+    }
+}
+
+/// One thread per client, up to `max_clients`, everyone after that is turned away
+fn serve(listener: TcpListener, shared: SharedFrame, clients: Arc<AtomicUsize>, max_clients: usize) {
+    for mut stream in listener.incoming().flatten() {
+        if clients.load(Ordering::Relaxed) >= max_clients {
+            info!("Turning away a spectator, {} are watching already", max_clients);
+            let _ = stream.write_all(b"HTTP/1.1 503 Service Unavailable\r\nContent-Length: 0\r\nConnection: close\r\n\r\n");
+            continue;
+        }
+
+        clients.fetch_add(1, Ordering::Relaxed);
+        let shared = shared.clone();
+        let client_count = clients.clone();
+        let spawned = thread::Builder::new()
+            .name("spectator-client".to_string())
+            .spawn(move || {
+                if let Err(e) = stream_to(stream, shared) {
+                    info!("Spectator disconnected: {}", e);
+                }
+                client_count.fetch_sub(1, Ordering::Relaxed);
+            });
+        if let Err(e) = spawned {
+            warn!("Failed to spawn a spectator client thread: {}", e);
+            clients.fetch_sub(1, Ordering::Relaxed);
+        }
+    }
+}
+
+fn stream_to(mut stream: TcpStream, shared: SharedFrame) -> std::io::Result<()> {
+    stream.set_write_timeout(Some(WRITE_TIMEOUT))?;
+    // whatever was requested, everyone gets the stream
+    let mut request = [0u8; 1024];
+    let _ = stream.read(&mut request)?;
+
+    write!(
+        stream,
+        "HTTP/1.1 200 OK\r\n\
+         Content-Type: multipart/x-mixed-replace; boundary={}\r\n\
+         Cache-Control: no-cache\r\n\
+         Access-Control-Allow-Origin: *\r\n\
+         Connection: close\r\n\r\n",
+        BOUNDARY,
+    )?;
+
+    let mut sequence = 0;
+    loop {
+        let jpeg = {
+            let (frame, changed) = &*shared;
+            let mut frame = frame.lock().unwrap();
+            while frame.sequence == sequence {
+                frame = changed.wait(frame).unwrap();
+            }
+            sequence = frame.sequence;
+            frame.jpeg.clone()
+        };
+
+        write!(
+            stream,
+            "--{}\r\nContent-Type: image/jpeg\r\nContent-Length: {}\r\n\r\n",
+            BOUNDARY,
+            jpeg.len(),
+        )?;
+        stream.write_all(&jpeg)?;
+        stream.write_all(b"\r\n")?;
+    }
+}
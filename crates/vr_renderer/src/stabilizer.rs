@@ -0,0 +1,283 @@
+use image::{DynamicImage, GrayImage};
+use tracing::debug_span;
+use messages::MaskSmoothing;
+
+/// Side length of the frames compared to follow the image between inference results
+const FLOW_SIZE: u32 = 80;
+/// How far the image may move between two results, in flow frame pixels
+const FLOW_SEARCH: i32 = 6;
+
+/// Blends consecutive masks so they fade instead of popping, and keeps pixels close to the
+/// threshold from flickering on and off
+pub struct MaskStabilizer {
+    settings: MaskSmoothing,
+    previous: Vec<GrayImage>,
+    visible: Vec<Vec<bool>>,
+}
+
+impl MaskStabilizer {
+    pub fn new(settings: &MaskSmoothing) -> Self {
+        Self {
+            settings: settings.clone(),
+            previous: vec![],
+            visible: vec![],
+        }
+    }
+
+    /// Masks in flight are kept, they just blend with the new settings from now on
+    pub fn configure(&mut self, settings: &MaskSmoothing) {
+        self.settings = settings.clone();
+    }
+
+    pub fn reset(&mut self) {
+        self.previous.clear();
+        self.visible.clear();
+    }
+
+    /// A missing mask means the model found nothing, so the previous one fades out
+    pub fn stabilize(&mut self, masks: Vec<Option<GrayImage>>) -> Vec<GrayImage> {
+        let _span = debug_span!("stabilize_masks").entered();
+        let blend = self.settings.blend.clamp(0.0, 1.0);
+        let show = (self.settings.show.clamp(0.0, 1.0) * 255.0) as u8;
+        let hide = (self.settings.hide.clamp(0.0, 1.0) * 255.0).min(show as f32) as u8;
+
+        masks
+            .into_iter()
+            .enumerate()
+            .map(|(id, mask)| {
+                let previous = self.previous.get(id);
+                let mut blended = match (mask, previous) {
+                    (Some(mask), Some(previous)) if mask.dimensions() == previous.dimensions() => {
+                        let mut mask = mask;
+                        mask.pixels_mut().zip(previous.pixels()).for_each(|(pixel, previous)| {
+                            pixel.0[0] = (pixel.0[0] as f32 * blend + previous.0[0] as f32 * (1.0 - blend)) as u8;
+                        });
+                        mask
+                    }
+                    (Some(mask), _) => mask,
+                    (None, Some(previous)) => {
+                        let mut faded = previous.clone();
+                        faded.pixels_mut().for_each(|pixel| {
+                            pixel.0[0] = (pixel.0[0] as f32 * (1.0 - blend)) as u8;
+                        });
+                        faded
+                    }
+                    (None, None) => GrayImage::new(1, 1),
+                };
+
+                if self.previous.len() > id {
+                    self.previous[id] = blended.clone();
+                } else {
+                    self.previous.push(blended.clone());
+                }
+
+                let len = (blended.width() * blended.height()) as usize;
+                if self.visible.len() <= id {
+                    self.visible.push(vec![]);
+                }
+                let visible = &mut self.visible[id];
+                if visible.len() != len {
+                    *visible = vec![false; len];
+                }
+
+                blended.pixels_mut().zip(visible.iter_mut()).for_each(|(pixel, visible)| {
+                    if pixel.0[0] >= show {
+                        *visible = true;
+                    } else if pixel.0[0] <= hide {
+                        *visible = false;
+                    }
+                    if !*visible {
+                        pixel.0[0] = 0;
+                    }
+                });
+
+                blended
+            })
+            .collect()
+    }
+}
+
+/// Small grayscale copy of a frame, cheap enough to compare on every rendered frame
+pub fn flow_frame(image: &DynamicImage) -> GrayImage {
+    image.thumbnail_exact(FLOW_SIZE, FLOW_SIZE).to_luma8()
+}
+
+/// Estimates how far the image moved between two flow frames and scales it to a mask of
+/// `mask_size`. This is a single translation for the whole frame, which is all a head turn is.
+pub fn flow_offset(from: &GrayImage, to: &GrayImage, mask_size: (u32, u32)) -> (i32, i32) {
+    let _span = debug_span!("flow_offset").entered();
+    if from.dimensions() != to.dimensions() {
+        return (0, 0);
+    }
+
+    let (width, height) = (from.width() as i32, from.height() as i32);
+    let (from_raw, to_raw) = (from.as_raw(), to.as_raw());
+    let cost = |dx: i32, dy: i32| {
+        let mut sum = 0u64;
+        let mut count = 0u64;
+        for y in 0.max(-dy)..height.min(height - dy) {
+            for x in 0.max(-dx)..width.min(width - dx) {
+                let a = from_raw[(y * width + x) as usize];
+                let b = to_raw[((y + dy) * width + x + dx) as usize];
+                sum += a.abs_diff(b) as u64;
+                count += 1;
+            }
+        }
+        sum as f32 / count.max(1) as f32
+    };
+
+    // Standing still wins ties, a blank frame should not drag the mask around
+    let mut best = ((0, 0), cost(0, 0));
+    for dy in -FLOW_SEARCH..=FLOW_SEARCH {
+        for dx in -FLOW_SEARCH..=FLOW_SEARCH {
+            let candidate = cost(dx, dy);
+            if candidate < best.1 {
+                best = ((dx, dy), candidate);
+            }
+        }
+    }
+
+    let ((dx, dy), _) = best;
+    (
+        dx * mask_size.0 as i32 / width,
+        dy * mask_size.1 as i32 / height,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use image::{GrayImage, Luma};
+    use messages::MaskSmoothing;
+    use super::{flow_offset, MaskStabilizer, FLOW_SIZE};
+
+    fn settings(blend: f32, show: f32, hide: f32) -> MaskSmoothing {
+        MaskSmoothing {
+            blend,
+            show,
+            hide,
+            optical_flow: false,
+        }
+    }
+
+    fn filled(value: u8) -> GrayImage {
+        GrayImage::from_pixel(4, 4, Luma([value]))
+    }
+
+    fn value(masks: &[GrayImage]) -> u8 {
+        masks[0].get_pixel(0, 0).0[0]
+    }
+
+    /// Texture without repeating patterns, so there is exactly one offset that matches
+    fn noise(x: i32, y: i32) -> u8 {
+        let mut hash = (x as u32).wrapping_mul(374_761_393) ^ (y as u32).wrapping_mul(668_265_263);
+        hash = (hash ^ (hash >> 13)).wrapping_mul(1_274_126_177);
+        (hash >> 24) as u8
+    }
+
+    fn shifted_noise(dx: i32, dy: i32) -> GrayImage {
+        GrayImage::from_fn(FLOW_SIZE, FLOW_SIZE, |x, y| Luma([noise(x as i32 - dx, y as i32 - dy)]))
+    }
+
+    #[test]
+    fn first_mask_only_shows_pixels_above_the_show_threshold() {
+        let mut stabilizer = MaskStabilizer::new(&settings(0.5, 0.5, 0.2));
+        let mut mask = filled(200);
+        mask.put_pixel(1, 1, Luma([100]));
+
+        let masks = stabilizer.stabilize(vec![Some(mask)]);
+        assert_eq!(masks[0].get_pixel(0, 0).0[0], 200);
+        assert_eq!(masks[0].get_pixel(1, 1).0[0], 0);
+    }
+
+    #[test]
+    fn masks_blend_and_keep_visible_pixels_until_the_hide_threshold() {
+        let mut stabilizer = MaskStabilizer::new(&settings(0.5, 0.5, 0.2));
+        assert_eq!(value(&stabilizer.stabilize(vec![Some(filled(200))])), 200);
+        // halfway between 0 and 200 is below `show`, but the pixel was visible already
+        assert_eq!(value(&stabilizer.stabilize(vec![Some(filled(0))])), 100);
+        // 50 is below `hide`
+        assert_eq!(value(&stabilizer.stabilize(vec![Some(filled(0))])), 0);
+    }
+
+    #[test]
+    fn missing_mask_fades_the_previous_one() {
+        let mut stabilizer = MaskStabilizer::new(&settings(0.5, 0.5, 0.2));
+        stabilizer.stabilize(vec![Some(filled(200))]);
+        assert_eq!(value(&stabilizer.stabilize(vec![None])), 100);
+        assert_eq!(value(&stabilizer.stabilize(vec![None])), 0);
+    }
+
+    #[test]
+    fn nothing_to_fade_is_an_empty_mask() {
+        let mut stabilizer = MaskStabilizer::new(&settings(0.5, 0.5, 0.2));
+        let masks = stabilizer.stabilize(vec![None, None]);
+        assert_eq!(masks.len(), 2);
+        assert_eq!(masks[0].dimensions(), (1, 1));
+        assert_eq!(value(&masks), 0);
+    }
+
+    #[test]
+    fn mask_of_another_size_is_not_blended() {
+        let mut stabilizer = MaskStabilizer::new(&settings(0.5, 0.5, 0.2));
+        stabilizer.stabilize(vec![Some(filled(200))]);
+        let masks = stabilizer.stabilize(vec![Some(GrayImage::from_pixel(2, 3, Luma([0])))]);
+        assert_eq!(masks[0].dimensions(), (2, 3));
+        assert_eq!(value(&masks), 0);
+    }
+
+    #[test]
+    fn masks_are_tracked_per_image() {
+        let mut stabilizer = MaskStabilizer::new(&settings(0.5, 0.5, 0.2));
+        stabilizer.stabilize(vec![Some(filled(200)), Some(filled(0))]);
+        let masks = stabilizer.stabilize(vec![Some(filled(0)), Some(filled(200))]);
+        assert_eq!(masks[0].get_pixel(0, 0).0[0], 100);
+        assert_eq!(masks[1].get_pixel(0, 0).0[0], 0);
+    }
+
+    #[test]
+    fn hide_threshold_never_exceeds_show() {
+        let mut stabilizer = MaskStabilizer::new(&settings(1.0, 0.2, 0.8));
+        stabilizer.stabilize(vec![Some(filled(200))]);
+        // would be hidden if `hide` was taken as it is
+        assert_eq!(value(&stabilizer.stabilize(vec![Some(filled(100))])), 100);
+    }
+
+    #[test]
+    fn configure_applies_to_the_next_mask() {
+        let mut stabilizer = MaskStabilizer::new(&settings(0.5, 0.5, 0.2));
+        stabilizer.stabilize(vec![Some(filled(200))]);
+        stabilizer.configure(&settings(1.0, 0.5, 0.2));
+        assert_eq!(value(&stabilizer.stabilize(vec![Some(filled(180))])), 180);
+    }
+
+    #[test]
+    fn reset_forgets_previous_masks() {
+        let mut stabilizer = MaskStabilizer::new(&settings(0.5, 0.5, 0.2));
+        stabilizer.stabilize(vec![Some(filled(200))]);
+        stabilizer.reset();
+        assert_eq!(value(&stabilizer.stabilize(vec![Some(filled(100))])), 0);
+    }
+
+    #[test]
+    fn flow_offset_finds_the_shift_and_scales_it_to_the_mask() {
+        let from = shifted_noise(0, 0);
+        assert_eq!(flow_offset(&from, &shifted_noise(3, -2), (FLOW_SIZE, FLOW_SIZE)), (3, -2));
+        assert_eq!(flow_offset(&from, &shifted_noise(3, -2), (FLOW_SIZE * 2, FLOW_SIZE * 4)), (6, -8));
+        assert_eq!(flow_offset(&from, &shifted_noise(-6, 6), (FLOW_SIZE, FLOW_SIZE)), (-6, 6));
+    }
+
+    #[test]
+    fn flow_offset_stays_put_without_movement() {
+        let frame = shifted_noise(0, 0);
+        assert_eq!(flow_offset(&frame, &frame, (640, 480)), (0, 0));
+
+        let blank = GrayImage::from_pixel(FLOW_SIZE, FLOW_SIZE, Luma([128]));
+        assert_eq!(flow_offset(&blank, &blank, (640, 480)), (0, 0));
+    }
+
+    #[test]
+    fn flow_offset_ignores_frames_of_different_sizes() {
+        let small = GrayImage::from_pixel(FLOW_SIZE / 2, FLOW_SIZE, Luma([0]));
+        assert_eq!(flow_offset(&shifted_noise(0, 0), &small, (640, 480)), (0, 0));
+    }
+}
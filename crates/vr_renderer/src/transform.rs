@@ -1,55 +1,131 @@
 use ggez::glam::Vec2;
-use messages::{EyeSettings, RenderSettingsData};
+use messages::{DisplayGeometry, EyeSettings, RenderSettingsData};
 
+/// Where and how large each image is drawn, in window pixels
 pub struct TransformSet {
     pub position_left_front: Vec2,
     pub position_left_back: Vec2,
     pub position_right_front: Vec2,
     pub position_right_back: Vec2,
+    pub size_left: Vec2,
+    pub size_right: Vec2,
 }
 
-const HEIGHT: i32 = 480;
-const WIDTH: i32 = 800;
+/// The headset screen as it ends up in the window. Everything is laid out in millimetres on the
+/// screen and only converted to pixels at the end, so a different panel or a desktop preview
+/// window just changes `pixels_per_mm`.
+#[derive(Clone, Copy, Debug)]
+pub struct Display {
+    /// Window size in pixels
+    pub size: Vec2,
+    pub size_mm: Vec2,
+    pub pixels_per_mm: f32,
+    /// Top left corner of the screen in the window, the screen is centered if the aspect ratios differ
+    pub origin: Vec2,
+    pub ipd_mm: f32,
+    pub lens_distance_mm: f32,
+    pub camera_fov_degrees: f32,
+    pub image_width_mm: Option<f32>,
+}
+
+/// Screen size in millimetres from its resolution and diagonal
+pub fn physical_size(geometry: &DisplayGeometry) -> Vec2 {
+    let resolution = Vec2::new(geometry.resolution.0.max(1) as f32, geometry.resolution.1.max(1) as f32);
+    resolution / geometry.pixels_per_mm()
+}
+
+impl Display {
+    pub fn new(geometry: &DisplayGeometry, window: (f32, f32)) -> Self {
+        let size = Vec2::new(window.0, window.1);
+        let size_mm = physical_size(geometry);
+        let pixels_per_mm = (size.x / size_mm.x).min(size.y / size_mm.y);
+
+        Display {
+            size,
+            size_mm,
+            pixels_per_mm,
+            origin: (size - size_mm * pixels_per_mm) / 2.0,
+            ipd_mm: geometry.ipd_mm,
+            lens_distance_mm: geometry.lens_distance_mm,
+            camera_fov_degrees: geometry.camera_fov_degrees,
+            image_width_mm: geometry.image_width_mm,
+        }
+    }
+
+    pub fn to_pixels(&self, mm: Vec2) -> Vec2 {
+        self.origin + mm * self.pixels_per_mm
+    }
+
+    /// Where the lens axis of an eye meets the screen, in millimetres from the top left
+    pub fn lens_axis(&self, left: bool) -> Vec2 {
+        let half_ipd = if left { -self.ipd_mm / 2.0 } else { self.ipd_mm / 2.0 };
+        Vec2::new(self.size_mm.x / 2.0 + half_ipd, self.size_mm.y / 2.0)
+    }
+
+    /// Each eye gets one half of the window
+    pub fn eye_viewport(&self) -> (f32, f32) {
+        (self.size.x / 2.0, self.size.y)
+    }
 
-fn vertical_center(eye_settings: &EyeSettings) -> f32 {
-    let combined_margin = HEIGHT - eye_settings.image_height as i32;
-    (combined_margin / 2) as f32
+    /// Top left corner of the right eye's half of the window
+    pub fn right_eye_origin(&self) -> Vec2 {
+        Vec2::new(self.size.x / 2.0, 0.0)
+    }
+
+    /// Lens axis within the eye's viewport, 0..1 from the top left
+    pub fn lens_center(&self, left: bool) -> (f32, f32) {
+        let (width, height) = self.eye_viewport();
+        let mut axis = self.to_pixels(self.lens_axis(left));
+        if !left {
+            axis -= self.right_eye_origin();
+        }
+        (axis.x / width.max(1.0), axis.y / height.max(1.0))
+    }
+
+    /// Drawn size of a camera image, so it covers the camera's field of view seen through the lens
+    pub fn image_size(&self, eye: &EyeSettings) -> Vec2 {
+        let width_mm = self.image_width_mm.unwrap_or_else(|| {
+            2.0 * self.lens_distance_mm * (self.camera_fov_degrees.to_radians() / 2.0).tan()
+        });
+        let height_mm = width_mm * eye.image_height as f32 / eye.image_width.max(1) as f32;
+        Vec2::new(width_mm, height_mm) * self.pixels_per_mm
+    }
+
+    /// How far the camera image has to move for the camera to turn by one radian
+    pub fn pixels_per_radian(&self, eye: &EyeSettings) -> f32 {
+        self.image_size(eye).x / self.camera_fov_degrees.to_radians().max(f32::EPSILON)
+    }
 }
 
 impl TransformSet {
-    pub fn from(config: &RenderSettingsData) -> Self {
+    /// Images are centered on their eye's lens axis and moved outwards by `space_between_*_mm`
+    pub fn from(config: &RenderSettingsData, display: &Display) -> Self {
+        let size_left = display.image_size(&config.left_eye);
+        let size_right = display.image_size(&config.right_eye);
+        let v_offset = config.v_offset_mm;
+
+        let left = |space: f32| display.to_pixels(
+            display.lens_axis(true) + Vec2::new(-space, v_offset)
+        ) - size_left / 2.0;
+        let right = |space: f32| display.to_pixels(
+            display.lens_axis(false) + Vec2::new(space, v_offset)
+        ) - size_right / 2.0;
+
         TransformSet {
-            position_left_back: Vec2 {
-                x: (WIDTH / 2 - config.space_between_back - (config.left_eye.image_width as i32)) as f32,
-                y: vertical_center(&config.left_eye) + config.v_offset as f32,
-            },
-            position_right_back: Vec2 {
-                x: (WIDTH / 2 + config.space_between_back) as f32,
-                y: vertical_center(&config.right_eye) + config.v_offset as f32,
-            },
-
-            position_left_front: Vec2 {
-                x: (WIDTH / 2 - config.space_between_front - (config.left_eye.image_width as i32)) as f32,
-                y: vertical_center(&config.left_eye) + config.v_offset as f32,
-            },
-            position_right_front: Vec2 {
-                x: (WIDTH / 2 + config.space_between_front) as f32,
-                y: vertical_center(&config.right_eye) + config.v_offset as f32,
-            },
+            position_left_back: left(config.space_between_back_mm),
+            position_right_back: right(config.space_between_back_mm),
+            position_left_front: left(config.space_between_front_mm),
+            position_right_front: right(config.space_between_front_mm),
+            size_left,
+            size_right,
         }
     }
 }
 
-pub fn left_offset_left(offset: &f32) -> Vec2 {
-    Vec2 {
-        x: WIDTH as f32 / 4.0 - offset,
-        y: HEIGHT as f32 / 2.0,
-    }
+pub fn left_offset_left(display: &Display, offset: &f32) -> Vec2 {
+    display.to_pixels(display.lens_axis(true) - Vec2::new(*offset, 0.0))
 }
 
-pub fn right_offset_right(offset: &f32) -> Vec2 {
-    Vec2 {
-        x: WIDTH as f32 * 3.0 / 4.0 + offset,
-        y: HEIGHT as f32 / 2.0,
-    }
-}
\ No newline at end of file
+pub fn right_offset_right(display: &Display, offset: &f32) -> Vec2 {
+    display.to_pixels(display.lens_axis(false) + Vec2::new(*offset, 0.0))
+}
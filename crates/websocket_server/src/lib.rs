@@ -1,49 +1,99 @@
-use std::net::TcpListener;
-use std::thread::{sleep, spawn};
+use std::io::ErrorKind;
+use std::net::{TcpListener, TcpStream};
+use std::thread::{sleep, spawn, JoinHandle};
 use std::time::Duration;
 use log::warn;
-use pub_sub::{PubSub};
+use pub_sub::{PubSub, Subscription};
 use tungstenite::{accept, Message};
 use messages::file_config::read_config;
-use messages::VrMessage;
+use messages::{LogMessageType, VrMessage};
 
-pub fn websocket_server(pub_sub: PubSub<VrMessage>) {
-    let server = TcpListener::bind("127.0.0.1:6342").unwrap();
-    for stream in server.incoming() {
-        let pub_sub = pub_sub.clone();
-        let subscriber_pub_sub = pub_sub.clone();
-        let subscriber = subscriber_pub_sub.subscribe();
-        let stream = stream.unwrap();
-        stream.set_nonblocking(true).unwrap();
-        spawn(move || {
-            let mut websocket = accept(stream).unwrap();
-
-            if let Ok(json) = serde_json::to_string(&VrMessage::PushRenderSettings {
-                data: read_config(),
-            }) {
+fn shutdown_requested(subscription: &Subscription<VrMessage>) -> bool {
+    let mut requested = false;
+    while let Ok(msg) = subscription.try_recv() {
+        requested |= matches!(msg, VrMessage::Shutdown {});
+    }
+    requested
+}
+
+fn serve(stream: TcpStream, pub_sub: PubSub<VrMessage>, subscriber: Subscription<VrMessage>) {
+    let mut websocket = accept(stream).unwrap();
+
+    let mut data = read_config();
+    // short PINs are easy to brute force from their hash, the wizard only needs to know there is one
+    data.access.pin_hash = data.access.pin_hash.map(|_| String::new());
+    if let Ok(json) = serde_json::to_string(&VrMessage::PushRenderSettings { data: Box::new(data) }) {
+        let _ = websocket.send(Message::Text(json));
+    }
+
+    loop {
+        if let Ok(msg) = subscriber.try_recv() {
+            if msg.contains_pin() {
+                // every client sees every message, PINs stay on the bus
+            } else if let Ok(json) = serde_json::to_string(&msg) {
                 let _ = websocket.send(Message::Text(json));
             }
 
-            loop {
-                if let Ok(msg) = subscriber.try_recv() {
-                    if let Ok(json) = serde_json::to_string(&msg) {
-                        let _ = websocket.send(Message::Text(json));
+            if let VrMessage::Shutdown {} = msg {
+                let _ = websocket.close(None);
+                // the stream is non-blocking, give the close frame a moment to go out
+                for _ in 0..20 {
+                    if websocket.flush().is_err() {
+                        break;
                     }
+                    sleep(Duration::from_millis(5));
                 }
+                return;
+            }
+        }
 
-                if let Ok(msg) = websocket.read() {
-                    if let Message::Text(text) = msg {
-                        if let Ok(msg) = serde_json::from_str(&text) {
-                            let _ = (&pub_sub).send(msg);
-                        }
+        match websocket.read() {
+            Ok(Message::Text(text)) => {
+                if let Ok(msg) = serde_json::from_str::<VrMessage>(&text) {
+                    if msg.is_privileged() {
+                        warn!("Refused unwrapped privileged command from websocket");
+                        let _ = (&pub_sub).send(VrMessage::Log {
+                            message: "Privileged commands have to be sent as Privileged".to_string(),
+                            message_type: LogMessageType::Error,
+                        });
                     } else {
-                        warn!("Received non-text message from websocket");
+                        let _ = (&pub_sub).send(msg);
                     }
                 }
-
-                sleep(Duration::from_millis(5));
             }
-        });
+            Ok(_) => {
+                warn!("Received non-text message from websocket");
+            }
+            Err(tungstenite::Error::ConnectionClosed | tungstenite::Error::AlreadyClosed) => return,
+            Err(_) => {}
+        }
+
+        sleep(Duration::from_millis(5));
     }
 }
 
+/// Serves the wizard until `VrMessage::Shutdown`, then closes every connection and waits for them
+pub fn websocket_server(pub_sub: PubSub<VrMessage>) {
+    let server = TcpListener::bind("127.0.0.1:6342").unwrap();
+    server.set_nonblocking(true).unwrap();
+    let shutdown = pub_sub.subscribe();
+    let mut connections: Vec<JoinHandle<()>> = vec![];
+
+    while !shutdown_requested(&shutdown) {
+        match server.accept() {
+            Ok((stream, _)) => {
+                let pub_sub = pub_sub.clone();
+                let subscriber = pub_sub.subscribe();
+                stream.set_nonblocking(true).unwrap();
+                connections.retain(|connection| !connection.is_finished());
+                connections.push(spawn(move || serve(stream, pub_sub, subscriber)));
+            }
+            Err(e) if e.kind() == ErrorKind::WouldBlock => sleep(Duration::from_millis(50)),
+            Err(e) => warn!("Failed to accept websocket connection: {}", e),
+        }
+    }
+
+    for connection in connections {
+        let _ = connection.join();
+    }
+}
@@ -11,3 +11,10 @@ model_down_onnx() {
 model_down_onnx yolov8m
 model_down_onnx yolo11s
 model_down_onnx yolo11m
+
+# dynamic input size, lets the CPU execution provider run at a reduced resolution
+yolo export format=onnx model=yolo11s-seg.pt simplify=true dynamic=true nms=true batch=2
+mv yolo11s-seg.onnx yolo11s-seg-dynamic.onnx
+
+# checked by the model registry before a model is loaded
+sha256sum *.onnx > SHA256SUMS
@@ -58,6 +58,7 @@ function App() {
     useEffect(() => {
         if (readyState === 1) {
             toast.dismiss("websocket-disconnected");
+            sendJsonMessage({ListModels: {}});
         } else {
             toast.error("Disconnected from server", {
                 id: "websocket-disconnected",
@@ -10,6 +10,12 @@ import CommandPalette, {
 } from "react-cmdk";
 import {
     copyToClipboard,
+    openDetectionsTab,
+    openLensTab,
+    openSpectatorTab,
+    openReprojectionTab,
+    openMaskSmoothingTab,
+    openUnitsTab,
     openGyroTab,
     openInferenceSettingsTab, openLeaderboardTab, openOptimizerInfoTab, openServoSettingsTab, openUtilitiesTab,
     openVrDistanceConfigurationTab, openWheelReadingTab,
@@ -47,12 +53,18 @@ function Cmdk(props: { dockview: DockviewApi | undefined }) {
                 items: [
                     view("Gyro", "Square3Stack3DIcon", openGyroTab.bind(null, api)),
                     view("VR Distance Configuration", "ViewfinderCircleIcon", openVrDistanceConfigurationTab.bind(null, api)),
+                    view("Lens Correction", "SunIcon", openLensTab.bind(null, api)),
                     view("Inference Settings", "ServerIcon", openInferenceSettingsTab.bind(null, api)),
                     view("Wheel Reading", "EllipsisHorizontalCircleIcon", openWheelReadingTab.bind(null, api)),
                     view("Servo Config", "AdjustmentsHorizontalIcon", openServoSettingsTab.bind(null, api)),
+                    view("Reprojection", "ArrowsPointingOutIcon", openReprojectionTab.bind(null, api)),
+                    view("Mask Smoothing", "SparklesIcon", openMaskSmoothingTab.bind(null, api)),
                     view("Optimizer Info", "PresentationChartLineIcon", openOptimizerInfoTab.bind(null, api)),
                     view("Leaderboard", "ClockIcon", openLeaderboardTab.bind(null, api)),
                     view("Utils", "CommandLineIcon", openUtilitiesTab.bind(null, api)),
+                    view("Detections", "EyeIcon", openDetectionsTab.bind(null, api)),
+                    view("Spectator", "VideoCameraIcon", openSpectatorTab.bind(null, api)),
+                    view("Units", "CpuChipIcon", openUnitsTab.bind(null, api)),
                 ],
             },
             {
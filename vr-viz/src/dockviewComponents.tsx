@@ -1,7 +1,14 @@
 import {DebouncedState} from "use-debounce";
 import {GyroReadingDisplay} from "./views/GyroReadingDisplay.tsx";
 import VrDistanceConfigurationDisplay from "./views/VrDistanceConfigurationDisplay.tsx";
-import {$inferenceReadings, $servoReading, $vrDistanceConfigurationReadings} from "./state.ts";
+import {
+    $inferenceReadings,
+    $lensReadings,
+    $maskSmoothingReading,
+    $reprojectionReading,
+    $servoReading,
+    $vrDistanceConfigurationReadings
+} from "./state.ts";
 import InferenceConfigurationDisplay from "./views/InferenceConfigurationDisplay.tsx";
 import {SendJsonMessage} from "react-use-websocket/dist/lib/types";
 import {WheelReadingDisplay} from "./views/WheelReadingDisplay.tsx";
@@ -9,11 +16,20 @@ import ServoConfigurationDisplay from "./views/ServoConfigurationDisplay.tsx";
 import OptimizerInfoDisplay from "./views/OptimizerInfoDisplay.tsx";
 import LeaderboardDisplay from "./views/LeaderboardDisplay.tsx";
 import UtilitiesDisplay from "./views/Utilities.tsx";
+import DetectionDisplay from "./views/DetectionDisplay.tsx";
+import LensConfigurationDisplay from "./views/LensConfigurationDisplay.tsx";
+import SpectatorDisplay from "./views/SpectatorDisplay.tsx";
+import ReprojectionDisplay from "./views/ReprojectionDisplay.tsx";
+import UnitsDisplay from "./views/UnitsDisplay.tsx";
+import MaskSmoothingDisplay from "./views/MaskSmoothingDisplay.tsx";
 
 export function DockviewComponents(useSetter: () => DebouncedState<SendJsonMessage>, setter: SendJsonMessage) {
     const vrSetter = useSetter();
     const infrSetter = useSetter();
     const servoSetter = useSetter();
+    const lensSetter = useSetter();
+    const reprojectionSetter = useSetter();
+    const smoothingSetter = useSetter();
 
     return {
         "gyro": () => <GyroReadingDisplay resetFn={() => {
@@ -25,18 +41,34 @@ export function DockviewComponents(useSetter: () => DebouncedState<SendJsonMessa
             $vrDistanceConfigurationReadings.set(json);
         }}/>,
 
+        "lens": () => <LensConfigurationDisplay setter={(json) => {
+            lensSetter(json);
+            $lensReadings.set(json);
+        }}/>,
+
         "infr": () => <InferenceConfigurationDisplay setter={(json) => {
-            infrSetter(json);
+            infrSetter({Privileged: {command: json}});
             $inferenceReadings.set(json);
         }}/>,
 
         "whl": () => <WheelReadingDisplay write={setter} />,
         "srvo": () => <ServoConfigurationDisplay setter={(v) => {
-            servoSetter(v)
+            servoSetter({Privileged: {command: v}})
             $servoReading.set(v);
         }} />,
-        "optm": () => <OptimizerInfoDisplay  setter={setter}/>,
+        "rprj": () => <ReprojectionDisplay setter={(v) => {
+            reprojectionSetter(v);
+            $reprojectionReading.set(v);
+        }}/>,
+        "msmo": () => <MaskSmoothingDisplay setter={(v) => {
+            smoothingSetter(v);
+            $maskSmoothingReading.set(v);
+        }}/>,
+        "optm": () => <OptimizerInfoDisplay setter={(json) => setter({Privileged: {command: json}})}/>,
         "ldbd": () => <LeaderboardDisplay setter={setter}/>,
-        "util": () => <UtilitiesDisplay setter={setter}/>
+        "util": () => <UtilitiesDisplay setter={setter}/>,
+        "dtct": () => <DetectionDisplay/>,
+        "spec": () => <SpectatorDisplay/>,
+        "unit": () => <UnitsDisplay setter={setter}/>
     }
 }
\ No newline at end of file
@@ -49,6 +49,42 @@ const utilsSettings = {
     "title": "Utilities",
 }
 
+const detectionSettings = {
+    "id": "dtct",
+    "component": "dtct",
+    "title": "Detections",
+}
+
+const lensSettings = {
+    "id": "lens",
+    "component": "lens",
+    "title": "Lens Correction",
+}
+
+const spectatorSettings = {
+    "id": "spec",
+    "component": "spec",
+    "title": "Spectator",
+}
+
+const reprojectionSettings = {
+    "id": "rprj",
+    "component": "rprj",
+    "title": "Reprojection",
+}
+
+const maskSmoothingSettings = {
+    "id": "msmo",
+    "component": "msmo",
+    "title": "Mask Smoothing",
+}
+
+const unitSettings = {
+    "id": "unit",
+    "component": "unit",
+    "title": "Units",
+}
+
 function openOrCreatePanel(api: DockviewApi, panelDefinition: AddPanelOptions) {
     const panelId = panelDefinition.id;
     const panel = api.getPanel(panelId);
@@ -92,6 +128,30 @@ export function openUtilitiesTab(api: DockviewApi) {
     openOrCreatePanel(api, utilsSettings);
 }
 
+export function openDetectionsTab(api: DockviewApi) {
+    openOrCreatePanel(api, detectionSettings);
+}
+
+export function openLensTab(api: DockviewApi) {
+    openOrCreatePanel(api, lensSettings);
+}
+
+export function openSpectatorTab(api: DockviewApi) {
+    openOrCreatePanel(api, spectatorSettings);
+}
+
+export function openReprojectionTab(api: DockviewApi) {
+    openOrCreatePanel(api, reprojectionSettings);
+}
+
+export function openMaskSmoothingTab(api: DockviewApi) {
+    openOrCreatePanel(api, maskSmoothingSettings);
+}
+
+export function openUnitsTab(api: DockviewApi) {
+    openOrCreatePanel(api, unitSettings);
+}
+
 export function copyToClipboard(api: DockviewApi) {
     const json = api.toJSON();
     navigator.clipboard.writeText(JSON.stringify(json, null, 2)).then(() => console.log("Copied to clipboard"));
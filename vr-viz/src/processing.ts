@@ -1,9 +1,11 @@
 import {LogMessage, WebsocketMessage} from "./types.ts";
 import {
+    $access,
+    $detections,
     $drvStateReading,
     $fpsReading,
     $gyroReadings,
-    $inferenceReadings, $leaderboard, $pedalReadings, $servoReading,
+    $inferenceReadings, $leaderboard, $lensReadings, $maskSmoothingReading, $modelList, $pedalReadings, $reprojectionReading, $servoReading, $session, $spectator, $units,
     $vrDistanceConfigurationReadings, $wheelReadings
 } from "./state.ts";
 import {terror, tinfo, tmessage, twarning} from "./toasties.ts";
@@ -21,15 +23,34 @@ export type Processors = {
 export const processors: Partial<Processors> = {
     GyroscopeReading: $gyroReadings.set,
     VrDistanceConfiguration: $vrDistanceConfigurationReadings.set,
+    LensConfiguration: $lensReadings.set,
     DriverStateUpdate: $drvStateReading.set,
     FPSUpdate: $fpsReading.set,
     WheelState: $wheelReadings.set,
     ModelConfiguration: $inferenceReadings.set,
     PedalState: $pedalReadings.set,
 
+    ModelList(msg) {
+        $modelList.set(msg.ModelList.models)
+    },
+    Detections(msg) {
+        $detections.set(msg.Detections.frames)
+    },
     PushTimerEntry(msg) {
         $leaderboard.set([...$leaderboard.get(), msg.PushTimerEntry.entry])
     },
+    Shutdown() {
+        twarning("ftVR is shutting down")
+    },
+    UnitStateUpdate(msg) {
+        $units.set(msg.UnitStateUpdate.states)
+    },
+    AccessState(msg) {
+        $access.set(msg.AccessState)
+    },
+    SessionState(msg) {
+        $session.set(msg.SessionState.phase)
+    },
     Log(log) {
         const functions: Record<LogMessage["Log"]["message_type"], (typeof tinfo)> = {
             Debug: tmessage, Error: terror, Info: tinfo, Warning: twarning
@@ -39,20 +60,22 @@ export const processors: Partial<Processors> = {
     PushRenderSettings(msg) {
         $vrDistanceConfigurationReadings.set({
             VrDistanceConfiguration: {
-                distance_between_b: msg.PushRenderSettings.data.space_between_back,
-                distance_between_f: msg.PushRenderSettings.data.space_between_front,
-                distance_between_u: msg.PushRenderSettings.data.space_between_ui,
-                v_offset: msg.PushRenderSettings.data.v_offset,
+                distance_between_b: msg.PushRenderSettings.data.space_between_back_mm,
+                distance_between_f: msg.PushRenderSettings.data.space_between_front_mm,
+                distance_between_u: msg.PushRenderSettings.data.space_between_ui_mm,
+                v_offset: msg.PushRenderSettings.data.v_offset_mm,
+            }
+        })
+        $lensReadings.set({
+            LensConfiguration: {
+                left: msg.PushRenderSettings.data.left_eye.lens,
+                right: msg.PushRenderSettings.data.right_eye.lens,
             }
         })
         $inferenceReadings.set({
             ModelConfiguration: {
-                model: msg.PushRenderSettings.data.model,
-                config: {
-                    confidence: msg.PushRenderSettings.data.model_configuration.confidence,
-                    iou: msg.PushRenderSettings.data.model_configuration.iou,
-                    kconf: msg.PushRenderSettings.data.model_configuration.kconf,
-                }
+                model: msg.PushRenderSettings.data.model_name,
+                config: msg.PushRenderSettings.data.model_configuration
             }
         })
         $servoReading.set({
@@ -60,7 +83,19 @@ export const processors: Partial<Processors> = {
                 config: msg.PushRenderSettings.data.servo_config
             }
         })
+        $reprojectionReading.set({
+            SetReprojection: {
+                config: msg.PushRenderSettings.data.reprojection
+            }
+        })
+        $maskSmoothingReading.set({
+            SetMaskSmoothing: {
+                config: msg.PushRenderSettings.data.mask_smoothing
+            }
+        })
         $leaderboard.set(msg.PushRenderSettings.data.leaderboard)
+        $spectator.set(msg.PushRenderSettings.data.spectator)
+        $access.set({...$access.get(), pin_configured: msg.PushRenderSettings.data.access.pin_hash !== null})
         toast.success("Loaded config", {
             dismissible: true,
             richColors: true,
@@ -1,8 +1,10 @@
 import {
+    AccessState,
+    DetectionFrame,
     DriverStateUpdate,
     FPSUpdate,
-    GyroMessage, LeaderboardEntry,
-    ModelConfiguration, PedalState, ServoConfiguration,
+    GyroMessage, LeaderboardEntry, LensConfiguration, LensDistortion,
+    ModelConfiguration, ModelInfo, PedalState, ServoConfiguration, SessionPhase, SetMaskSmoothing, SetReprojection, SpectatorConfig, UnitState,
     VrDistanceConfiguration,
     WheelState
 } from "./types.ts";
@@ -16,13 +18,40 @@ export const $vrDistanceConfigurationReadings = atom<VrDistanceConfiguration>({
     VrDistanceConfiguration: {v_offset: 0, distance_between_b: 0, distance_between_f: 0, distance_between_u: 0}
 });
 
+export const defaultLens: LensDistortion = {
+    k1: 0,
+    k2: 0,
+    center: [0.5, 0.5],
+    scale: 1,
+    chromatic_aberration: [1, 1, 1],
+};
+
+export const $lensReadings = atom<LensConfiguration>({
+    LensConfiguration: {left: defaultLens, right: defaultLens}
+});
+
 export const $inferenceReadings = atom<ModelConfiguration>({
     ModelConfiguration: {
-        model: "YoloV8mInt8ONNX",
+        model: "yolo11s-seg.onnx",
         config: {
             iou: 0.5,
             confidence: 0.5,
-            kconf: 0.5
+            kconf: 0.5,
+            device: "Auto",
+            cpu_threads: 0,
+            cpu_input_size: 320,
+            classes: [
+                {name: "person", confidence: null, opacity: 1},
+                {name: "clock", confidence: null, opacity: 1},
+            ],
+            segmenter: "Yolo",
+            color_key: {
+                color_space: "YCrCb",
+                ranges: [[0, 255], [133, 173], [77, 127]],
+                invert: false,
+                cleanup: 2,
+                width: 320,
+            }
         }
     }
 });
@@ -39,6 +68,8 @@ export const $drvStateReading = atom<DriverStateUpdate>({
     DriverStateUpdate: {states: [{Offline: {name: "Backend"}}]}
 });
 
+export const $units = atom<UnitState[]>([]);
+
 export const $fpsReading = atom<FPSUpdate>({
     FPSUpdate: {fps: 0}
 });
@@ -53,4 +84,39 @@ export const $servoReading = atom<ServoConfiguration>({
     }
 });
 
-export const $leaderboard = atom<LeaderboardEntry[]>([]);
\ No newline at end of file
+export const $reprojectionReading = atom<SetReprojection>({
+    SetReprojection: {
+        config: {
+            enabled: false,
+            strength: 1,
+            servo_speed: 10,
+        }
+    }
+});
+
+export const $maskSmoothingReading = atom<SetMaskSmoothing>({
+    SetMaskSmoothing: {
+        config: {
+            blend: 0.6,
+            show: 0.35,
+            hide: 0.15,
+            optical_flow: false,
+        }
+    }
+});
+
+export const $leaderboard = atom<LeaderboardEntry[]>([]);
+
+export const $session = atom<SessionPhase>("Off");
+
+export const $access = atom<AccessState["AccessState"]>({
+    unlocked: false,
+    locked_out_for: 0,
+    pin_configured: false,
+});
+
+export const $modelList = atom<ModelInfo[]>([]);
+
+export const $detections = atom<DetectionFrame[]>([]);
+
+export const $spectator = atom<SpectatorConfig | null>(null);
\ No newline at end of file
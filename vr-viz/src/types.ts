@@ -16,6 +16,46 @@ export type VrDistanceConfiguration = {
     }
 }
 
+export type LensDistortion = {
+    k1: number;
+    k2: number;
+    center: [number, number];
+    scale: number;
+    chromatic_aberration: [number, number, number];
+}
+
+export type EyeSettings = {
+    image_width: number;
+    image_height: number;
+    lens: LensDistortion;
+}
+
+export type DisplayGeometry = {
+    resolution: [number, number];
+    diagonal_inches: number;
+    ipd_mm: number;
+    lens_distance_mm: number;
+    camera_fov_degrees: number;
+    image_width_mm: number | null;
+}
+
+export type SpectatorConfig = {
+    enabled: boolean;
+    view: "Left" | "Right" | "Both";
+    address: string;
+    max_fps: number;
+    width: number;
+    quality: number;
+    max_clients: number;
+}
+
+export type LensConfiguration = {
+    LensConfiguration: {
+        left: LensDistortion;
+        right: LensDistortion;
+    }
+}
+
 export type LogMessage = {
     Log: {
         message: string;
@@ -23,28 +63,91 @@ export type LogMessage = {
     }
 }
 
-export const models = [
-    "YoloV8mInt8ONNX",
-    "YoloV8mHalfONNX",
-    "YoloV8mFullONNX",
-    "YoloV11sInt8ONNX",
-    "YoloV11sHalfONNX",
-    "YoloV11sFullONNX",
-    "YoloV11mInt8ONNX",
-    "YoloV11mHalfONNX",
-    "YoloV11mFullONNX",
-] as const;
+export type ModelInfo = {
+    name: string;
+    task: string;
+    classes: string[];
+    image_size: [number, number] | null;
+    dynamic_input: boolean;
+    checksum: string;
+    verified: boolean | null;
+    error: string | null;
+}
+
+export function isCompatible(model: ModelInfo) {
+    return model.error === null && model.task === "segment" && model.verified !== false;
+}
+
+export type ListModels = {
+    ListModels: Record<string, never>
+}
+
+export type ModelList = {
+    ModelList: {
+        models: ModelInfo[];
+    }
+}
+
+export type Detection = {
+    class: string;
+    confidence: number;
+    bbox: [number, number, number, number];
+}
+
+export type DetectionFrame = {
+    width: number;
+    height: number;
+    detections: Detection[];
+}
+
+export type Detections = {
+    Detections: {
+        frames: DetectionFrame[];
+    }
+}
+
+export const inferenceDevices = ["Auto", "TensorRT", "Cuda", "Cpu"] as const;
+
+export type InferenceDevice = typeof inferenceDevices[number];
+
+export type MaskClass = {
+    name: string;
+    confidence: number | null;
+    opacity: number;
+}
 
-export type Model = typeof models[number];
+export const segmenters = ["Auto", "Yolo", "ColorKey"] as const;
+
+export type Segmenter = typeof segmenters[number];
+
+export const colorSpaces = ["YCrCb", "Hsv"] as const;
+
+export type ColorSpace = typeof colorSpaces[number];
+
+export type ColorKeyConfig = {
+    color_space: ColorSpace;
+    ranges: [[number, number], [number, number], [number, number]];
+    invert: boolean;
+    cleanup: number;
+    width: number;
+}
+
+export type InferenceConfig = {
+    confidence: number;
+    iou: number;
+    kconf: number;
+    device: InferenceDevice;
+    cpu_threads: number;
+    cpu_input_size: number;
+    classes: MaskClass[];
+    segmenter: Segmenter;
+    color_key: ColorKeyConfig;
+}
 
 export type ModelConfiguration = {
     ModelConfiguration: {
-        model: Model;
-        config: {
-            confidence: number;
-            iou: number;
-            kconf: number;
-        }
+        model: string;
+        config: InferenceConfig
     }
 }
 
@@ -58,6 +161,38 @@ export type ServoConfiguration = {
     }
 }
 
+export type Reprojection = {
+    enabled: boolean;
+    strength: number;
+    servo_speed: number;
+}
+
+export type SetReprojection = {
+    SetReprojection: {
+        config: Reprojection;
+    }
+}
+
+export type MaskSmoothing = {
+    blend: number;
+    show: number;
+    hide: number;
+    optical_flow: boolean;
+}
+
+export type SetMaskSmoothing = {
+    SetMaskSmoothing: {
+        config: MaskSmoothing;
+    }
+}
+
+export type CameraServoPosition = {
+    CameraServoPosition: {
+        yaw: number;
+        pitch: number;
+    }
+}
+
 export type LeaderboardEntry = {
     name: string;
     time: number;
@@ -67,24 +202,29 @@ export type LeaderboardEntry = {
 export type PushRenderSettings = {
     PushRenderSettings: {
         data: {
-            left_eye: unknown;
-            right_eye: unknown;
-            v_offset: number;
-            space_between_back: number;
-            space_between_front: number;
-            space_between_ui: number;
-            model: Model;
-            model_configuration: {
-                confidence: number;
-                iou: number;
-                kconf: number;
-            },
+            left_eye: EyeSettings;
+            right_eye: EyeSettings;
+            v_offset_mm: number;
+            space_between_back_mm: number;
+            space_between_front_mm: number;
+            space_between_ui_mm: number;
+            layout_version: number;
+            model_name: string;
+            model_configuration: InferenceConfig,
             servo_config: {
                 steer_offset: number;
                 yaw_offset: number;
                 pitch_offset: number;
             },
             leaderboard: LeaderboardEntry[];
+            display: DisplayGeometry;
+            spectator: SpectatorConfig;
+            reprojection: Reprojection;
+            mask_smoothing: MaskSmoothing;
+            access: {
+                // emptied by the server, only tells whether a PIN is set
+                pin_hash: string | null;
+            };
         }
     }
 }
@@ -149,10 +289,38 @@ export type TimerStart = {
     }
 }
 
+export type AskPlayerName = {
+    AskPlayerName: Record<string, never>
+}
+
+export type SessionPhase = "Off" | "Idle" | "PlayerSetup" | "CalibrationCheck" | "Countdown" | "Driving" | "Finished" | "Aborted" | "Results";
+
+export type StartSession = {
+    StartSession: Record<string, never>
+}
+
+export type AbortSession = {
+    AbortSession: Record<string, never>
+}
+
+export type SessionState = {
+    SessionState: {
+        phase: SessionPhase;
+    }
+}
+
+export type CancelInterface = {
+    CancelInterface: Record<string, never>
+}
+
 export type TimerEnd = {
     TimerEnd: Record<string, never>
 }
 
+export type TimerLap = {
+    TimerLap: Record<string, never>
+}
+
 export type PushTimerEntry = {
     PushTimerEntry: {
         entry: LeaderboardEntry;
@@ -165,10 +333,74 @@ export type DeleteTimerEntry = {
     }
 }
 
+export type UnitStatus = "Starting" | "Running" | "Restarting" | "Disabled";
+
+export type UnitState = {
+    name: string;
+    status: UnitStatus;
+    errors: number;
+    restarts: number;
+}
+
+export type UnitStateUpdate = {
+    UnitStateUpdate: {
+        states: UnitState[];
+    }
+}
+
+export type SetUnitEnabled = {
+    SetUnitEnabled: {
+        name: string;
+        enabled: boolean;
+    }
+}
+
+export type Shutdown = {
+    Shutdown: Record<string, never>
+}
+
+export type Privileged = {
+    Privileged: {
+        command: WebsocketMessage;
+    }
+}
+
+export type Unlock = {
+    Unlock: Record<string, never>
+}
+
+export type Lock = {
+    Lock: Record<string, never>
+}
+
+export type VerifyOperatorPin = {
+    VerifyOperatorPin: {
+        pin: string;
+    }
+}
+
+export type SetPin = {
+    SetPin: {
+        pin: string;
+    }
+}
+
+export type AccessState = {
+    AccessState: {
+        unlocked: boolean;
+        locked_out_for: number;
+        pin_configured: boolean;
+    }
+}
+
 export type WebsocketMessage = GyroMessage
     | VrDistanceConfiguration
+    | LensConfiguration
     | LogMessage
     | ModelConfiguration
+    | ListModels
+    | ModelList
+    | Detections
     | PushRenderSettings
     | WheelState
     | DriverStateUpdate
@@ -178,16 +410,38 @@ export type WebsocketMessage = GyroMessage
     | PedalState
     | ZeroPedal
     | ServoConfiguration
+    | SetReprojection
+    | SetMaskSmoothing
+    | CameraServoPosition
     | TimerStart
+    | AskPlayerName
+    | StartSession
+    | AbortSession
+    | SessionState
+    | CancelInterface
     | TimerEnd
+    | TimerLap
     | PushTimerEntry
     | DeleteTimerEntry
+    | Privileged
+    | Unlock
+    | Lock
+    | VerifyOperatorPin
+    | SetPin
+    | AccessState
+    | UnitStateUpdate
+    | SetUnitEnabled
+    | Shutdown
     ;
 
 export type FullWebsocketMessage = GyroMessage
     & VrDistanceConfiguration
+    & LensConfiguration
     & LogMessage
     & ModelConfiguration
+    & ListModels
+    & ModelList
+    & Detections
     & PushRenderSettings
     & WheelState
     & DriverStateUpdate
@@ -197,8 +451,26 @@ export type FullWebsocketMessage = GyroMessage
     & PedalState
     & ZeroPedal
     & ServoConfiguration
+    & SetReprojection
+    & SetMaskSmoothing
+    & CameraServoPosition
     & TimerStart
+    & AskPlayerName
+    & StartSession
+    & AbortSession
+    & SessionState
+    & CancelInterface
     & TimerEnd
+    & TimerLap
     & PushTimerEntry
     & DeleteTimerEntry
+    & Privileged
+    & Unlock
+    & Lock
+    & VerifyOperatorPin
+    & SetPin
+    & AccessState
+    & UnitStateUpdate
+    & SetUnitEnabled
+    & Shutdown
     ;
@@ -0,0 +1,49 @@
+import {useStore} from "@nanostores/react";
+import {$detections} from "../state.ts";
+import {DetectionFrame} from "../types.ts";
+
+const colors = ["#60a5fa", "#f87171", "#4ade80", "#facc15", "#c084fc"];
+
+function colorFor(name: string) {
+    let hash = 0;
+    for (const char of name) {
+        hash = (hash * 31 + char.charCodeAt(0)) | 0;
+    }
+    return colors[Math.abs(hash) % colors.length];
+}
+
+function FrameDisplay({frame, title}: { frame: DetectionFrame, title: string }) {
+    return (
+        <div className="flex flex-col gap-2 items-center">
+            <span>{title}</span>
+            <svg viewBox={`0 0 ${frame.width} ${frame.height}`} className="w-64 bg-stone-800">
+                {frame.detections.map((detection, i) => {
+                    const [x, y, width, height] = detection.bbox;
+                    const color = colorFor(detection.class);
+                    return (
+                        <g key={i}>
+                            <rect x={x} y={y} width={width} height={height} fill="none" stroke={color} strokeWidth={3}/>
+                            <text x={x + 4} y={y + 24} fill={color} fontSize={22}>
+                                {detection.class} {detection.confidence.toFixed(2)}
+                            </text>
+                        </g>
+                    )
+                })}
+            </svg>
+        </div>
+    )
+}
+
+export default function DetectionDisplay() {
+    const frames = useStore($detections);
+    // the renderer segments the right eye first
+    const titles = ["Right", "Left"];
+
+    return (
+        <div className="w-full h-full flex gap-4 justify-center items-center padding-around">
+            {frames.length === 0 ? <span>No detections yet</span> : frames.map((frame, i) => (
+                <FrameDisplay key={i} frame={frame} title={titles[i] ?? `Frame ${i}`}/>
+            ))}
+        </div>
+    )
+}
@@ -1,10 +1,13 @@
-import {ModelConfiguration} from "../types.ts";
-import {$inferenceReadings} from "../state.ts";
+import {ColorKeyConfig, colorSpaces, inferenceDevices, MaskClass, ModelConfiguration, segmenters} from "../types.ts";
+import {$inferenceReadings, $modelList} from "../state.ts";
 import {useStore} from "@nanostores/react";
 
 function InferenceConfigurationDisplay(props: { setter: (_: ModelConfiguration) => void }) {
     const {setter} = props;
     const reading = useStore($inferenceReadings);
+    const models = useStore($modelList);
+    const selectedModel = models.find((model) => model.name === reading.ModelConfiguration.model);
+    const knownClasses = selectedModel?.classes ?? [];
 
     function patchConf(partial: Partial<ModelConfiguration["ModelConfiguration"]["config"]>) {
         setter({
@@ -18,6 +21,36 @@ function InferenceConfigurationDisplay(props: { setter: (_: ModelConfiguration)
         });
     }
 
+    function patchColorKey(partial: Partial<ColorKeyConfig>) {
+        patchConf({
+            color_key: {
+                ...reading.ModelConfiguration.config.color_key,
+                ...partial
+            }
+        });
+    }
+
+    function patchRange(channel: number, bound: 0 | 1, value: number) {
+        const ranges = reading.ModelConfiguration.config.color_key.ranges.map(
+            (range) => [...range]
+        ) as ColorKeyConfig["ranges"];
+        ranges[channel][bound] = value;
+        patchColorKey({ranges});
+    }
+
+    const colorKey = reading.ModelConfiguration.config.color_key;
+    const channelNames = colorKey.color_space === "Hsv" ? ["H", "S", "V"] : ["Y", "Cr", "Cb"];
+
+    function patchClass(index: number, partial: Partial<MaskClass> | null) {
+        const classes = [...reading.ModelConfiguration.config.classes];
+        if (partial === null) {
+            classes.splice(index, 1);
+        } else {
+            classes[index] = {...classes[index], ...partial};
+        }
+        patchConf({classes});
+    }
+
     return (
         <div className="padding-around">
             <label>Intersection Over Union</label><br/>
@@ -43,7 +76,96 @@ function InferenceConfigurationDisplay(props: { setter: (_: ModelConfiguration)
                        patchConf({
                            kconf: Number(e.target.value),
                        })
-                   }}/>
+                   }}/><br/> <br/>
+            <label>Segmenter</label><br/>
+            <select value={reading.ModelConfiguration.config.segmenter}
+                    onChange={(e) => {
+                        patchConf({
+                            segmenter: e.target.value as never,
+                        })
+                    }}>
+                {segmenters.map((segmenter) => (
+                    <option key={segmenter} value={segmenter}>{segmenter}</option>
+                ))}
+            </select><br/> <br/>
+            <label>Device</label><br/>
+            <select value={reading.ModelConfiguration.config.device}
+                    onChange={(e) => {
+                        patchConf({
+                            device: e.target.value as never,
+                        })
+                    }}>
+                {inferenceDevices.map((device) => (
+                    <option key={device} value={device}>{device}</option>
+                ))}
+            </select><br/> <br/>
+            <label>CPU Threads (0 = auto)</label><br/>
+            <input type="number" value={reading.ModelConfiguration.config.cpu_threads} min={0} max={64}
+                   onChange={(e) => {
+                       patchConf({
+                           cpu_threads: Number(e.target.value),
+                       })
+                   }}/><br/> <br/>
+            <label>CPU Input Size{selectedModel && !selectedModel.dynamic_input ? " (fixed by the model)" : ""}</label><br/>
+            <input type="number" value={reading.ModelConfiguration.config.cpu_input_size} min={160} max={640} step={32}
+                   disabled={selectedModel !== undefined && !selectedModel.dynamic_input}
+                   title="Only models exported with dynamic height and width can run at a different size"
+                   onChange={(e) => {
+                       patchConf({
+                           cpu_input_size: Number(e.target.value),
+                       })
+                   }}/><br/> <br/>
+            <label>Color Key</label><br/>
+            <select value={colorKey.color_space}
+                    onChange={(e) => patchColorKey({color_space: e.target.value as never})}>
+                {colorSpaces.map((space) => (
+                    <option key={space} value={space}>{space}</option>
+                ))}
+            </select>
+            <label>
+                <input type="checkbox" checked={colorKey.invert}
+                       onChange={(e) => patchColorKey({invert: e.target.checked})}/> Invert
+            </label><br/>
+            {colorKey.ranges.map((range, channel) => (
+                <div key={channel}>
+                    <label>{channelNames[channel]}</label>
+                    <input type="number" value={range[0]} step={colorKey.color_space === "Hsv" && channel > 0 ? 0.01 : 1}
+                           onChange={(e) => patchRange(channel, 0, Number(e.target.value))}/>
+                    <input type="number" value={range[1]} step={colorKey.color_space === "Hsv" && channel > 0 ? 0.01 : 1}
+                           onChange={(e) => patchRange(channel, 1, Number(e.target.value))}/>
+                </div>
+            ))}
+            <label>Cleanup Radius</label><br/>
+            <input type="number" value={colorKey.cleanup} min={0} max={10}
+                   onChange={(e) => patchColorKey({cleanup: Number(e.target.value)})}/><br/>
+            <label>Key Width</label><br/>
+            <input type="number" value={colorKey.width} min={80} max={640} step={40}
+                   onChange={(e) => patchColorKey({width: Number(e.target.value)})}/><br/> <br/>
+            <label>Mask Classes</label><br/>
+            <datalist id="known-classes">
+                {knownClasses.map((name) => (
+                    <option key={name} value={name}/>
+                ))}
+            </datalist>
+            {reading.ModelConfiguration.config.classes.map((maskClass, index) => (
+                <div key={index}>
+                    <input type="text" list="known-classes" value={maskClass.name}
+                           onChange={(e) => patchClass(index, {name: e.target.value})}/>
+                    <button onClick={() => patchClass(index, null)}>x</button><br/>
+                    <label>Confidence</label>
+                    <input type="range" min={0} max={1} step={0.01}
+                           value={maskClass.confidence ?? reading.ModelConfiguration.config.confidence}
+                           title={maskClass.confidence === null ? "Global" : String(maskClass.confidence)}
+                           onChange={(e) => patchClass(index, {confidence: Number(e.target.value)})}/><br/>
+                    <label>Opacity</label>
+                    <input type="range" min={0} max={1} step={0.01} value={maskClass.opacity}
+                           title={String(maskClass.opacity)}
+                           onChange={(e) => patchClass(index, {opacity: Number(e.target.value)})}/>
+                </div>
+            ))}
+            <button onClick={() => patchConf({
+                classes: [...reading.ModelConfiguration.config.classes, {name: "", confidence: null, opacity: 1}]
+            })}>Add class</button>
         </div>
     )
 }
@@ -1,4 +1,4 @@
-import {$leaderboard} from "../state.ts";
+import {$leaderboard, $session} from "../state.ts";
 import {useStore} from "@nanostores/react";
 import {SendJsonMessage} from "react-use-websocket/dist/lib/types";
 import {useState} from "react";
@@ -6,14 +6,24 @@ import {useState} from "react";
 function LeaderboardDisplay({setter}: { setter: SendJsonMessage }) {
     const reading = useStore($leaderboard);
     const [running, setRunning] = useState(false);
+    const session = useStore($session);
+    const sessionRunning = session !== "Idle" && session !== "Off";
 
     function deleteLeaderboard(id: number) {
-        setter({DeleteTimerEntry: {id}});
+        setter({Privileged: {command: {DeleteTimerEntry: {id}}}});
         $leaderboard.set(reading.filter((entry) => entry.id !== id));
     }
 
     return (
         <div className="padding-around flex flex-col gap-2">
+            <div className="flex gap-2 items-center">
+                <button onClick={() => setter({StartSession: {}})} disabled={running || sessionRunning || session === "Off"}
+                        title="Name, calibration check, countdown, run and results in the headset">Start session
+                </button>
+                <button onClick={() => setter({AbortSession: {}})} disabled={!sessionRunning}>Abort session
+                </button>
+                <div>Phase: {session}</div>
+            </div>
             <div className="flex gap-2">
                 <input type={"text"} placeholder={"Name"} id={"name"} disabled={running}/>
                 <button onClick={() => {
@@ -22,10 +32,19 @@ function LeaderboardDisplay({setter}: { setter: SendJsonMessage }) {
                     setRunning(true);
                 }} disabled={running}>Start
                 </button>
+                <button onClick={() => {
+                    setter({AskPlayerName: {}});
+                    setRunning(true);
+                }} disabled={running} title="The player types their name with the wheel">Start in headset
+                </button>
+                <button onClick={() => {
+                    setter({TimerLap: {}});
+                }} disabled={!running && session !== "Driving"}>Lap
+                </button>
                 <button onClick={() => {
                     setter({TimerEnd: {}});
                     setRunning(false);
-                }} disabled={!running}>Stop
+                }} disabled={!running && session !== "Driving"}>Stop
                 </button>
             </div>
 
@@ -0,0 +1,69 @@
+import {LensConfiguration, LensDistortion} from "../types.ts";
+import {$lensReadings} from "../state.ts";
+import {useStore} from "@nanostores/react";
+
+type Eye = "left" | "right";
+
+function LensConfigurationDisplay(props: { setter: (_: LensConfiguration) => void }) {
+    const {setter} = props;
+    const reading = useStore($lensReadings);
+
+    function patchLens(eye: Eye, partial: Partial<LensDistortion>) {
+        setter({
+            LensConfiguration: {
+                ...reading.LensConfiguration,
+                [eye]: {
+                    ...reading.LensConfiguration[eye],
+                    ...partial
+                }
+            }
+        });
+    }
+
+    function slider(label: string, value: number, min: number, max: number, step: number,
+                    onChange: (value: number) => void) {
+        return <>
+            <label>{label}</label><br/>
+            <input type="range" value={value} min={min} max={max} step={step} title={String(value)}
+                   onChange={(e) => onChange(Number(e.target.value))}/><br/> <br/>
+        </>;
+    }
+
+    function eyeSettings(eye: Eye) {
+        const lens = reading.LensConfiguration[eye];
+        const [red, green, blue] = lens.chromatic_aberration;
+
+        return <div key={eye}>
+            <h3>{eye === "left" ? "Left Eye" : "Right Eye"}</h3>
+            {slider("k1", lens.k1, -0.5, 0.5, 0.005, (k1) => patchLens(eye, {k1}))}
+            {slider("k2", lens.k2, -0.5, 0.5, 0.005, (k2) => patchLens(eye, {k2}))}
+            {slider("Center X", lens.center[0], 0, 1, 0.005,
+                (x) => patchLens(eye, {center: [x, lens.center[1]]}))}
+            {slider("Center Y", lens.center[1], 0, 1, 0.005,
+                (y) => patchLens(eye, {center: [lens.center[0], y]}))}
+            {slider("Scale", lens.scale, 0.5, 1.5, 0.005, (scale) => patchLens(eye, {scale}))}
+            {slider("Red", red, 0.95, 1.05, 0.001,
+                (r) => patchLens(eye, {chromatic_aberration: [r, green, blue]}))}
+            {slider("Green", green, 0.95, 1.05, 0.001,
+                (g) => patchLens(eye, {chromatic_aberration: [red, g, blue]}))}
+            {slider("Blue", blue, 0.95, 1.05, 0.001,
+                (b) => patchLens(eye, {chromatic_aberration: [red, green, b]}))}
+        </div>;
+    }
+
+    return (
+        <div className="padding-around">
+            {eyeSettings("left")}
+            {eyeSettings("right")}
+            <button onClick={() => setter({
+                LensConfiguration: {
+                    ...reading.LensConfiguration,
+                    right: reading.LensConfiguration.left
+                }
+            })}>Copy left to right
+            </button>
+        </div>
+    )
+}
+
+export default LensConfigurationDisplay
@@ -0,0 +1,62 @@
+import {MaskSmoothing, SetMaskSmoothing} from "../types.ts";
+import {$maskSmoothingReading} from "../state.ts";
+import {useStore} from "@nanostores/react";
+
+function MaskSmoothingDisplay(props: { setter: (_: SetMaskSmoothing) => void }) {
+    const {setter} = props;
+    const reading = useStore($maskSmoothingReading);
+    const config = reading.SetMaskSmoothing.config;
+
+    function patch(partial: Partial<MaskSmoothing>) {
+        setter({
+            SetMaskSmoothing: {
+                config: {
+                    ...config,
+                    ...partial
+                }
+            }
+        });
+    }
+
+    return (
+        <div className="padding-around">
+            <label>Blend (1 = no blending)</label><br/>
+            <input type="range" value={config.blend} min={0.05} max={1} step={0.05}
+                   title={String(config.blend)}
+                   onChange={(e) => {
+                       patch({
+                           blend: Number(e.target.value),
+                       })
+                   }}/><br/> <br/>
+
+            <label>Show above</label><br/>
+            <input type="range" value={config.show} min={0} max={1} step={0.05}
+                   title={String(config.show)}
+                   onChange={(e) => {
+                       const show = Number(e.target.value);
+                       patch({
+                           show,
+                           hide: Math.min(config.hide, show),
+                       })
+                   }}/><br/> <br/>
+
+            <label>Hide below</label><br/>
+            <input type="range" value={config.hide} min={0} max={1} step={0.05}
+                   title={String(config.hide)}
+                   onChange={(e) => {
+                       const hide = Number(e.target.value);
+                       patch({
+                           hide,
+                           show: Math.max(config.show, hide),
+                       })
+                   }}/><br/> <br/>
+
+            <label>
+                <input type="checkbox" checked={config.optical_flow}
+                       onChange={(e) => patch({optical_flow: e.target.checked})}/> Follow the camera image
+            </label><br/> <br/>
+        </div>
+    )
+}
+
+export default MaskSmoothingDisplay;
@@ -1,9 +1,10 @@
-import {ModelConfiguration, models} from "../types.ts";
+import {isCompatible, ModelConfiguration} from "../types.ts";
 import {useStore} from "@nanostores/react";
-import {$inferenceReadings} from "../state.ts";
+import {$inferenceReadings, $modelList} from "../state.ts";
 
 export default function OptimizerInfoDisplay({setter}: { setter: (_: ModelConfiguration) => void }) {
     const reading = useStore($inferenceReadings);
+    const models = useStore($modelList);
 
     function patch(partial: Partial<ModelConfiguration["ModelConfiguration"]>) {
         setter({
@@ -23,7 +24,10 @@ export default function OptimizerInfoDisplay({setter}: { setter: (_: ModelConfig
                                              })
                                          }}>
                 {models.map((model) => (
-                    <option key={model} value={model}>{model}</option>
+                    <option key={model.name} value={model.name} disabled={!isCompatible(model)}
+                            title={model.error ?? `${model.task}, ${model.classes.length} classes, sha256 ${model.checksum}`}>
+                        {model.name}{model.verified === false ? " (checksum mismatch)" : ""}
+                    </option>
                 ))}
             </select> <br /> <br />
             <div style={{
@@ -0,0 +1,49 @@
+import {Reprojection, SetReprojection} from "../types.ts";
+import {$reprojectionReading} from "../state.ts";
+import {useStore} from "@nanostores/react";
+
+function ReprojectionDisplay(props: { setter: (_: SetReprojection) => void }) {
+    const {setter} = props;
+    const reading = useStore($reprojectionReading);
+    const config = reading.SetReprojection.config;
+
+    function patch(partial: Partial<Reprojection>) {
+        setter({
+            SetReprojection: {
+                config: {
+                    ...config,
+                    ...partial
+                }
+            }
+        });
+    }
+
+    return (
+        <div className="padding-around">
+            <label>
+                <input type="checkbox" checked={config.enabled}
+                       onChange={(e) => patch({enabled: e.target.checked})}/> Enabled
+            </label><br/> <br/>
+
+            <label>Strength</label><br/>
+            <input type="range" value={config.strength} min={0} max={2} step={0.05}
+                   title={String(config.strength)}
+                   onChange={(e) => {
+                       patch({
+                           strength: Number(e.target.value),
+                       })
+                   }}/><br/> <br/>
+
+            <label>Servo Speed (rad/s)</label><br/>
+            <input type="range" value={config.servo_speed} min={1} max={30} step={0.5}
+                   title={String(config.servo_speed)}
+                   onChange={(e) => {
+                       patch({
+                           servo_speed: Number(e.target.value),
+                       })
+                   }}/><br/> <br/>
+        </div>
+    )
+}
+
+export default ReprojectionDisplay;
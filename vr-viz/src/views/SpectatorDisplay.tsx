@@ -0,0 +1,26 @@
+import {useStore} from "@nanostores/react";
+import {$spectator} from "../state.ts";
+
+function SpectatorDisplay() {
+    const spectator = useStore($spectator);
+
+    if (spectator === null || !spectator.enabled) {
+        return (
+            <div className="padding-around">
+                The spectator stream is disabled, set <code>spectator.enabled</code> in <code>conf.ron</code>.
+            </div>
+        )
+    }
+
+    const port = spectator.address.split(":").pop();
+    const url = `http://${window.location.hostname}:${port}/`;
+
+    return (
+        <div className="padding-around flex flex-col gap-2 h-full">
+            <img src={url} alt="Spectator stream" className="w-full h-full object-contain bg-black"/>
+            <a href={url} target="_blank" rel="noreferrer">Open in a new window</a>
+        </div>
+    )
+}
+
+export default SpectatorDisplay
@@ -0,0 +1,27 @@
+import {useStore} from "@nanostores/react";
+import {SendJsonMessage} from "react-use-websocket/dist/lib/types";
+import {$units} from "../state.ts";
+
+function UnitsDisplay({setter}: { setter: SendJsonMessage }) {
+    const units = useStore($units);
+
+    if (units.length === 0) {
+        return <div className="padding-around">No unit states received yet</div>
+    }
+
+    return (
+        <div className="padding-around flex flex-col gap-2">
+            {units.map((unit) => (
+                <div key={unit.name} className="flex gap-2 items-center">
+                    <input type="checkbox" checked={unit.status !== "Disabled"}
+                           onChange={(e) => setter({SetUnitEnabled: {name: unit.name, enabled: e.target.checked}})}/>
+                    <div className="w-[12ch]">{unit.name}</div>
+                    <span className={"pill " + (unit.status === "Running" ? "up" : "down")}>{unit.status}</span>
+                    <div title="errors / restarts">{unit.errors} / {unit.restarts}</div>
+                </div>
+            ))}
+        </div>
+    )
+}
+
+export default UnitsDisplay;
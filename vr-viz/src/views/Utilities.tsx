@@ -1,16 +1,68 @@
 import {SendJsonMessage} from "react-use-websocket/dist/lib/types";
+import {useStore} from "@nanostores/react";
+import {useState} from "react";
+import {$access} from "../state.ts";
+
+function AccessControl({setter}: { setter: SendJsonMessage }) {
+    const access = useStore($access);
+    const [pin, setPin] = useState("");
+    const [newPin, setNewPin] = useState("");
+
+    const status = !access.pin_configured
+        ? "No operator PIN set"
+        : access.locked_out_for > 0
+            ? `Locked out for ${access.locked_out_for}s`
+            : access.unlocked ? "Unlocked" : "Locked";
+
+    return (
+        <div className="flex flex-col gap-2">
+            <div>Operator access: {status}</div>
+            <div className="flex gap-2">
+                <input type="password" placeholder="PIN" value={pin} onChange={(e) => setPin(e.target.value)}/>
+                <button onClick={() => {
+                    setter({VerifyOperatorPin: {pin}});
+                    setPin("");
+                }} disabled={!access.pin_configured}>Unlock
+                </button>
+                <button onClick={() => setter({Unlock: {}})} disabled={!access.pin_configured}
+                        title="Enter the PIN with the wheel">In headset
+                </button>
+                <button onClick={() => setter({Lock: {}})} disabled={!access.unlocked}>Lock</button>
+            </div>
+            <div className="flex gap-2">
+                <input type="password" placeholder="New PIN" value={newPin} onChange={(e) => setNewPin(e.target.value)}/>
+                <button onClick={() => {
+                    setter({Privileged: {command: {SetPin: {pin: newPin}}}});
+                    setNewPin("");
+                }} title="An empty PIN removes it">Set PIN
+                </button>
+            </div>
+        </div>
+    )
+}
 
 function UtilitiesDisplay({setter}: { setter: SendJsonMessage }) {
     return (
         <div className="padding-around flex flex-col gap-2">
+            <AccessControl setter={setter}/>
             <button onClick={() => setter({
                 AskPin: {
                     length: 3
                 }
             })}>Begin Pinentry
             </button>
+            <button onClick={() => setter({
+                CancelInterface: {}
+            })}>Cancel Headset Prompt
+            </button>
+            <button onClick={() => {
+                if (confirm("Stop the car and shut ftVR down?")) {
+                    setter({Shutdown: {}});
+                }
+            }}>Shut down
+            </button>
         </div>
     )
 }
 
-export default UtilitiesDisplay;
\ No newline at end of file
+export default UtilitiesDisplay;
@@ -8,8 +8,9 @@ function VrDistanceConfigurationDisplay(props: { setter: (vdfc: VrDistanceConfig
 
     return (
         <div className="padding-around">
-            <label>x-Distance-f</label><br/>
-            <input type="range" value={vrDistanceConfigurationReading.VrDistanceConfiguration.distance_between_f}
+            <label>x-Distance-f (mm)</label><br/>
+            <input type="range" value={vrDistanceConfigurationReading.VrDistanceConfiguration.distance_between_f} min={-20} max={20} step={0.1}
+                   title={`${vrDistanceConfigurationReading.VrDistanceConfiguration.distance_between_f.toFixed(1)} mm`}
                    onChange={(e) => {
                        setter({
                            VrDistanceConfiguration: {
@@ -20,8 +21,9 @@ function VrDistanceConfigurationDisplay(props: { setter: (vdfc: VrDistanceConfig
                            }
                        })
                    }}/> <br/> <br/>
-            <label>x-Distance-b</label><br/>
-            <input type="range" value={vrDistanceConfigurationReading.VrDistanceConfiguration.distance_between_b}
+            <label>x-Distance-b (mm)</label><br/>
+            <input type="range" value={vrDistanceConfigurationReading.VrDistanceConfiguration.distance_between_b} min={-20} max={20} step={0.1}
+                   title={`${vrDistanceConfigurationReading.VrDistanceConfiguration.distance_between_b.toFixed(1)} mm`}
                    onChange={(e) => {
                        setter({
                            VrDistanceConfiguration: {
@@ -32,8 +34,9 @@ function VrDistanceConfigurationDisplay(props: { setter: (vdfc: VrDistanceConfig
                            }
                        })
                    }}/> <br/> <br/>
-            <label>x-Distance-UI</label><br/>
-            <input type="range" value={vrDistanceConfigurationReading.VrDistanceConfiguration.distance_between_u}
+            <label>x-Distance-UI (mm)</label><br/>
+            <input type="range" value={vrDistanceConfigurationReading.VrDistanceConfiguration.distance_between_u} min={-20} max={20} step={0.1}
+                   title={`${vrDistanceConfigurationReading.VrDistanceConfiguration.distance_between_u.toFixed(1)} mm`}
                    onChange={(e) => {
                        setter({
                            VrDistanceConfiguration: {
@@ -45,8 +48,9 @@ function VrDistanceConfigurationDisplay(props: { setter: (vdfc: VrDistanceConfig
                        })
                    }}/>
             <br/> <br/>
-            <label>v-Offset</label><br/>
-            <input type="range" value={vrDistanceConfigurationReading.VrDistanceConfiguration.v_offset}
+            <label>v-Offset (mm)</label><br/>
+            <input type="range" value={vrDistanceConfigurationReading.VrDistanceConfiguration.v_offset} min={-20} max={20} step={0.1}
+                   title={`${vrDistanceConfigurationReading.VrDistanceConfiguration.v_offset.toFixed(1)} mm`}
                    onChange={(e) => {
                        setter({
                            VrDistanceConfiguration: {
@@ -68,8 +68,8 @@ function ActionButtons({write}: {write: (jsonMessage: WebsocketMessage) => void}
             <Button onClick={write.bind(null, {ResetWheel: {}})}>Whl Rst</Button>
             <Button onClick={write.bind(null, {FlipWheelBtns: {flip: !wheel.WheelState.flipped}})}>Btn Flp</Button>
             <div className="bg-stone-700 w-px h-3/4" />
-            <Button onClick={write.bind(null, {ZeroPedal: { position: "Lower" }})}>Lwr cal</Button>
-            <Button onClick={write.bind(null, {ZeroPedal: { position: "Upper" }})}>Upr cal</Button>
+            <Button onClick={write.bind(null, {Privileged: {command: {ZeroPedal: { position: "Lower" }}}})}>Lwr cal</Button>
+            <Button onClick={write.bind(null, {Privileged: {command: {ZeroPedal: { position: "Upper" }}}})}>Upr cal</Button>
         </ButtonList>
     )
 }